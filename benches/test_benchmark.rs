@@ -6,7 +6,7 @@ fn test_parse_sequence_record_by_positions(record: &[u8]) {
 }
 
 fn test_parse_sequence_record(record: &[u8]) {
-    (_, _) = parse_sequence_record(record);
+    _ = parse_sequence_record(record);
 }
 
 fn alignment_benchmark(c: &mut Criterion) {
@@ -0,0 +1,136 @@
+use crate::gff::{entries, escape_seqid, parent, seqid, strand, Entry, Role};
+use crate::location::{Span, Strand};
+use crate::{FeatureType, Sequence};
+use std::io::{self, Write};
+
+/// Writes the features of `sequence` as BED6, a line for each stretch of a joined location,
+/// all named by the feature's GFF3 ID.
+///
+/// Coordinates are 0-based and half-open, as BED requires, so a stretch across the origin of a
+/// circular record is written as two. The source feature, which covers the whole record, is
+/// left out.
+pub fn write_bed<W: Write>(mut writer: W, sequence: &Sequence) -> io::Result<()> {
+    let seqid = escape_seqid(seqid(sequence));
+    for entry in bed_entries(sequence, &seqid) {
+        for span in &entry.spans {
+            writeln!(
+                writer,
+                "{}\t{}\t{}\t{}\t0\t{}",
+                seqid,
+                span.start - 1,
+                span.end,
+                entry.id,
+                strand(span)
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes the features of `sequence` as BED12, a line for each feature with a block for each
+/// stretch of its location.
+///
+/// The thick part is the CDS itself, and for a transcript the CDSs linked to it as in GFF3;
+/// other features have none. Overlapping stretches, e.g. at a ribosomal slippage site, are
+/// merged into one block. The source feature is left out.
+pub fn write_bed12<W: Write>(mut writer: W, sequence: &Sequence) -> io::Result<()> {
+    let seqid = escape_seqid(seqid(sequence));
+    let entries = bed_entries(sequence, &seqid);
+    for entry in &entries {
+        let (start, end) = entry.extent();
+        let (thick_start, thick_end) = match entry.role {
+            _ if *entry.feature_type == FeatureType::Cds => (start - 1, end),
+            Role::Transcript => entries
+                .iter()
+                .filter(|other| {
+                    *other.feature_type == FeatureType::Cds
+                        && parent(other, &entries) == Some(entry.id.as_str())
+                })
+                .map(|cds| cds.extent())
+                .reduce(|(start, end), (other_start, other_end)| {
+                    (start.min(other_start), end.max(other_end))
+                })
+                .map_or((start - 1, start - 1), |(start, end)| (start - 1, end)),
+            _ => (start - 1, start - 1),
+        };
+
+        // blocks in order along the sequence, relative to the start of the feature
+        let mut blocks: Vec<(usize, usize)> = Vec::new();
+        let mut spans: Vec<(usize, usize)> = entry
+            .spans
+            .iter()
+            .map(|span| (span.start - 1, span.end))
+            .collect();
+        spans.sort_unstable();
+        for (block_start, block_end) in spans {
+            match blocks.last_mut() {
+                Some((_, last_end)) if block_start <= *last_end => {
+                    *last_end = (*last_end).max(block_end);
+                }
+                _ => blocks.push((block_start, block_end)),
+            }
+        }
+        let sizes: Vec<String> = blocks
+            .iter()
+            .map(|(block_start, block_end)| (block_end - block_start).to_string())
+            .collect();
+        let starts: Vec<String> = blocks
+            .iter()
+            .map(|(block_start, _)| (block_start + 1 - start).to_string())
+            .collect();
+
+        writeln!(
+            writer,
+            "{}\t{}\t{}\t{}\t0\t{}\t{}\t{}\t0\t{}\t{},\t{},",
+            seqid,
+            start - 1,
+            end,
+            entry.id,
+            entry.strand(),
+            thick_start,
+            thick_end,
+            blocks.len(),
+            sizes.join(","),
+            starts.join(",")
+        )?;
+    }
+    Ok(())
+}
+
+/// The entries of the GFF3 features but the source, whose spans all start at 1 or later and
+/// end no earlier than they start, so the coordinates above can't underflow.
+///
+/// BED has no way to go past the end of a circular sequence, so a span across its origin is
+/// split there into two, in reading order.
+fn bed_entries<'a>(sequence: &'a Sequence, seqid: &str) -> Vec<Entry<'a>> {
+    let length = sequence.locus.length;
+    let mut entries = entries(sequence, seqid);
+    entries.retain(|entry| *entry.feature_type != FeatureType::Source);
+    for entry in &mut entries {
+        entry.spans = entry
+            .spans
+            .iter()
+            .flat_map(|&span| {
+                if span.end <= length {
+                    return vec![span];
+                }
+                let before = Span {
+                    end: length,
+                    partial_end: false,
+                    ..span
+                };
+                let after = Span {
+                    start: 1,
+                    end: span.end - length,
+                    partial_start: false,
+                    ..span
+                };
+                match span.strand {
+                    Strand::Forward => vec![before, after],
+                    Strand::Reverse => vec![after, before],
+                }
+            })
+            .collect();
+    }
+    entries
+}
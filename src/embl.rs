@@ -0,0 +1,276 @@
+use crate::header::{self, LengthUnit, Locus, Topology};
+use crate::{
+    finish_record, is_residue, offset_in, parse_features, record_accession, remove_quotes,
+    trim_ascii, DataType, FeatureType, ParseError, Protein, Sequence,
+};
+use chrono::NaiveDate;
+
+/// Column where the data of a line starts, after its two letter code.
+const EMBL_INDENT: usize = 5;
+/// Column where feature locations and qualifiers start, once the `FT` code is cut off.
+const FEATURE_QUALIFIER_INDENT: usize = 19;
+
+/// Taxonomic divisions of the ID line, see section 3.2 of the EMBL user manual
+const DIVISIONS: [&[u8]; 15] = [
+    b"PHG", b"ENV", b"FUN", b"HUM", b"INV", b"MAM", b"VRT", b"MUS", b"PLN", b"PRO", b"ROD", b"SYN",
+    b"TGN", b"UNC", b"VRL",
+];
+/// Data classes of the ID line, and `standard` from the ID lines before release 87
+const DATA_CLASSES: [&[u8]; 13] = [
+    b"CON",
+    b"PAT",
+    b"EST",
+    b"GSS",
+    b"HTC",
+    b"HTG",
+    b"MGA",
+    b"WGS",
+    b"TSA",
+    b"STS",
+    b"STD",
+    b"TPA",
+    b"standard",
+];
+
+/// `ID` at the start of a line, followed by whitespace.
+#[inline]
+pub(crate) fn is_id_line(line: &[u8]) -> bool {
+    line.strip_prefix(b"ID")
+        .is_some_and(|rest| rest.first().is_some_and(u8::is_ascii_whitespace))
+}
+
+/// Parses the ID line into the LOCUS fields and the sequence version, returning a short reason
+/// on failure.
+///
+/// Both `ID   X56734; SV 1; linear; mRNA; STD; PLN; 1859 BP.` and the older
+/// `ID   X56734   standard; RNA; PLN; 1859 BP.` are read, tokens after the accession being
+/// identified by their content as on LOCUS lines. The molecule type is shortened to its last
+/// word, e.g. `DNA` for `genomic DNA`, as GenBank writes it. Only a missing accession or length
+/// is an error; other tokens that aren't recognised are kept as the division or ignored.
+fn parse_id(line: &[u8]) -> Result<(Locus, Option<&[u8]>), &'static str> {
+    let rest = line
+        .strip_prefix(b"ID")
+        .ok_or("line does not start with ID")?;
+    let mut parts = rest.split(|&b| b == b';').map(trim_ascii);
+
+    let mut locus = Locus {
+        name: parts
+            .next()
+            .and_then(|part| part.split(u8::is_ascii_whitespace).next())
+            .filter(|name| !name.is_empty())
+            .ok_or("ID line without an accession")?
+            .to_vec(),
+        ..Locus::default()
+    };
+    let mut version = None;
+    let mut length = None;
+
+    for part in parts.filter(|part| !part.is_empty()) {
+        let words: Vec<&[u8]> = part
+            .split(u8::is_ascii_whitespace)
+            .filter(|word| !word.is_empty())
+            .collect();
+        match words.as_slice() {
+            [b"SV", number] => version = Some(*number),
+            [topology] if Topology::from_bytes(topology).is_some() => {
+                locus.topology = Topology::from_bytes(topology);
+            }
+            [division] if DIVISIONS.contains(division) => locus.division = division.to_vec(),
+            [data_class] if DATA_CLASSES.contains(data_class) => (),
+            [number, unit] if matches!(*unit, b"BP." | b"BP" | b"AA." | b"AA") => {
+                locus.unit = if unit.starts_with(b"AA") {
+                    LengthUnit::AminoAcids
+                } else {
+                    LengthUnit::BasePairs
+                };
+                length = std::str::from_utf8(number)
+                    .ok()
+                    .and_then(|n| n.parse().ok());
+            }
+            [.., molecule_type] if locus.molecule_type.is_empty() => {
+                locus.molecule_type = molecule_type.to_vec();
+            }
+            // an unfamiliar data class or division, kept as the division until a known one,
+            // which comes last, turns up
+            [division] if !DIVISIONS.contains(&locus.division.as_slice()) => {
+                locus.division = division.to_vec();
+            }
+            _ => (),
+        }
+    }
+
+    locus.length = length.ok_or("ID line without a sequence length")?;
+    Ok((locus, version))
+}
+
+/// The date an entry was last updated, from the last of its DT lines, e.g.
+/// `DT   25-NOV-2005 (Rel. 85, Last updated, Version 11)`.
+fn parse_date(lines: &[&[u8]]) -> Option<NaiveDate> {
+    let date = lines.last()?.split(u8::is_ascii_whitespace).next()?;
+    NaiveDate::parse_from_str(std::str::from_utf8(date).ok()?, "%d-%b-%Y").ok()
+}
+
+/// `RP   1-1859, 1900-2000` as the base ranges of a GenBank REFERENCE line,
+/// `(bases 1 to 1859; 1900 to 2000)`.
+fn reference_positions(data: &[u8]) -> Vec<u8> {
+    let ranges: Vec<Vec<u8>> = data
+        .split(|&b| b == b',')
+        .map(trim_ascii)
+        .filter(|range| !range.is_empty())
+        .map(|range| {
+            range
+                .split(|&b| b == b'-')
+                .collect::<Vec<_>>()
+                .join(&b" to "[..])
+        })
+        .collect();
+    let mut positions = b" (bases ".to_vec();
+    positions.extend_from_slice(&ranges.join(&b"; "[..]));
+    positions.push(b')');
+    positions
+}
+
+/// Parses an EMBL flat file record into the same structures as `parse_sequence_record`.
+///
+/// Line codes are mapped onto their GenBank counterparts: ID and DT make up the LOCUS line, AC
+/// and SV the accessions and version, DE, KW, OS, OC and CC the DEFINITION, KEYWORDS,
+/// ORGANISM, taxonomy and COMMENT, PR the BioProject DBLINK, and the R lines the references.
+/// The FT lines are read as the GenBank feature table they are laid out like. The organism is
+/// taken from the source feature if it has one, as OS adds the common name.
+pub fn parse_embl_record(record: &[u8]) -> Result<(Sequence, Vec<Protein>), ParseError> {
+    let accession = record_accession(record);
+    if let Err(err) = std::str::from_utf8(record) {
+        return Err(ParseError::InvalidUtf8 {
+            accession,
+            offset: err.valid_up_to(),
+        });
+    }
+
+    let mut sequence = Sequence::default();
+    let mut features = Vec::new();
+    let mut raw_header = header::RawHeader::default();
+    let mut version: Vec<u8> = Vec::new();
+
+    let mut lines = record.split(|&b| b == b'\n').peekable();
+    while let Some(line) = lines.next() {
+        // records straight from a file may still end with their `//` line
+        if line.trim_ascii_end() == b"//" {
+            break;
+        }
+        if trim_ascii(line).is_empty() {
+            continue;
+        }
+        if line.len() < 2 {
+            return Err(ParseError::LineTooShort {
+                accession,
+                offset: offset_in(record, line),
+            });
+        }
+
+        // a field is every consecutive line with the same code
+        let code = &line[..2];
+        if code == b"SQ" {
+            // the sequence is the last section of a record, its lines numbered at the end
+            sequence.sequence = lines
+                .by_ref()
+                .take_while(|line| line.trim_ascii_end() != b"//")
+                .flatten()
+                .filter(|&b| is_residue(b))
+                .copied()
+                .collect();
+            break;
+        }
+        let field: Vec<&[u8]> = std::iter::once(line)
+            .chain(std::iter::from_fn(|| {
+                lines.next_if(|line| line.starts_with(code))
+            }))
+            .collect();
+        let data: Vec<&[u8]> = field
+            .iter()
+            .map(|line| trim_ascii(line.get(EMBL_INDENT..).unwrap_or_default()))
+            .collect();
+        let text = data.join(&b' ');
+
+        match code {
+            b"ID" => {
+                let (locus, sequence_version) =
+                    parse_id(line).map_err(|reason| ParseError::InvalidLocus {
+                        accession: accession.clone(),
+                        reason,
+                    })?;
+                if let Some(sequence_version) = sequence_version {
+                    version = [&locus.name[..], sequence_version].join(&b'.');
+                }
+                sequence.locus = locus;
+            }
+            b"DT" => sequence.locus.date = parse_date(&data),
+            // `AC   X56734; S46826;`
+            b"AC" => sequence
+                .accession
+                .extend(text.iter().map(|&b| if b == b';' { b' ' } else { b })),
+            b"SV" => version = text,
+            b"PR" => {
+                // `PR   Project:PRJNA12345;`
+                let project = text.strip_prefix(b"Project:").unwrap_or(&text);
+                let mut dblink = b"BioProject: ".to_vec();
+                dblink.extend_from_slice(project.strip_suffix(b";").unwrap_or(project));
+                raw_header.append_data(&DataType::DbLink, &dblink);
+            }
+            b"DE" => sequence.definition = text,
+            b"KW" => raw_header.append_data(&DataType::Keywords, &text),
+            b"OS" => sequence.organism = text,
+            b"OC" => sequence.taxonomy = text,
+            b"CC" => sequence.comment = data.join(&b'\n'),
+            b"RN" => {
+                // `RN   [5]`
+                raw_header.references.push(Default::default());
+                let number = text.strip_prefix(b"[").unwrap_or(&text);
+                let number = number.strip_suffix(b"]").unwrap_or(number);
+                raw_header.append_data(&DataType::Reference, number);
+            }
+            b"RP" => raw_header.append_data(&DataType::Reference, &reference_positions(&text)),
+            b"RX" => {
+                // `RX   PUBMED; 1907511.`, other databases such as DOI are left out
+                for line in &data {
+                    if let Some(pubmed) = line.strip_prefix(b"PUBMED;") {
+                        let pubmed = trim_ascii(pubmed);
+                        let pubmed = pubmed.strip_suffix(b".").unwrap_or(pubmed);
+                        raw_header.append_data(&DataType::Pubmed, pubmed);
+                    }
+                }
+            }
+            b"RG" => raw_header.append_data(&DataType::Consortium, &text),
+            b"RA" => {
+                let authors = text.strip_suffix(b";").unwrap_or(&text);
+                raw_header.append_data(&DataType::Authors, authors);
+            }
+            b"RT" => {
+                // `RT   "Nucleotide sequence of ...";`, or just `;` without a title
+                let title = text.strip_suffix(b";").unwrap_or(&text);
+                raw_header.append_data(&DataType::Title, remove_quotes(trim_ascii(title)));
+            }
+            b"RL" => raw_header.append_data(&DataType::Journal, &text),
+            b"RC" => raw_header.append_data(&DataType::Remark, &text),
+            b"FT" => {
+                // without the `FT`, the lines are laid out as in GenBank, two columns further left
+                let feature_lines = field.iter().map(|line| &line[2..]);
+                features =
+                    parse_features(feature_lines, record, &accession, FEATURE_QUALIFIER_INDENT)?;
+            }
+            // XX spacers, the FH header of the feature table and lines without a GenBank
+            // counterpart, such as DR and OG
+            _ => (),
+        }
+    }
+
+    sequence.version = version;
+    if let Some(organism) = features
+        .iter()
+        .find(|feature| feature.feature_type == Some(FeatureType::Source))
+        .and_then(|source| source.qualifier(b"organism"))
+    {
+        sequence.organism = organism.to_vec();
+    }
+
+    Ok(finish_record(sequence, raw_header, features))
+}
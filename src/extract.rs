@@ -0,0 +1,148 @@
+use crate::location::{GapLength, Location, LocationError};
+use std::fmt;
+
+/// Error raised while extracting the bases covered by a location.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExtractError {
+    InvalidLocation(LocationError),
+    /// 1-based inclusive range outside of a sequence of `length` bases
+    OutOfBounds {
+        start: usize,
+        end: usize,
+        length: usize,
+    },
+    /// the location refers to another record
+    RemoteLocation(String),
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for ExtractError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExtractError::InvalidLocation(error) => write!(f, "{}", error),
+            ExtractError::OutOfBounds { start, end, length } => write!(
+                f,
+                "range {}..{} is outside of a sequence of length {}",
+                start, end, length
+            ),
+            ExtractError::RemoteLocation(accession) => {
+                write!(f, "location refers to another record: {}", accession)
+            }
+            ExtractError::Unsupported(reason) => write!(f, "cannot extract {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ExtractError {}
+
+impl From<LocationError> for ExtractError {
+    fn from(error: LocationError) -> Self {
+        ExtractError::InvalidLocation(error)
+    }
+}
+
+/// Complement of a nucleotide, including the IUPAC ambiguity codes; case is kept.
+#[inline]
+pub fn complement_base(base: u8) -> u8 {
+    let complement = match base.to_ascii_uppercase() {
+        b'A' => b'T',
+        b'T' | b'U' => b'A',
+        b'G' => b'C',
+        b'C' => b'G',
+        b'R' => b'Y',
+        b'Y' => b'R',
+        b'K' => b'M',
+        b'M' => b'K',
+        b'B' => b'V',
+        b'V' => b'B',
+        b'D' => b'H',
+        b'H' => b'D',
+        // S, W, N and gaps are their own complement
+        other => other,
+    };
+    if base.is_ascii_lowercase() {
+        complement.to_ascii_lowercase()
+    } else {
+        complement
+    }
+}
+
+pub fn reverse_complement(sequence: &[u8]) -> Vec<u8> {
+    sequence.iter().rev().map(|&b| complement_base(b)).collect()
+}
+
+impl Location {
+    /// Bases of `sequence` covered by this location, spliced and reverse complemented as needed.
+    ///
+    /// On a `circular` sequence a range whose start is after its end wraps around the origin,
+    /// e.g. `4500..100`. Gaps of known length are filled with `n`, and are an error if longer
+    /// than `sequence`.
+    pub fn extract(&self, sequence: &[u8], circular: bool) -> Result<Vec<u8>, ExtractError> {
+        let mut extracted = Vec::new();
+        self.extract_into(sequence, circular, &mut extracted)?;
+        Ok(extracted)
+    }
+
+    fn extract_into(
+        &self,
+        sequence: &[u8],
+        circular: bool,
+        extracted: &mut Vec<u8>,
+    ) -> Result<(), ExtractError> {
+        let length = sequence.len();
+        let out_of_bounds =
+            |start: usize, end: usize| ExtractError::OutOfBounds { start, end, length };
+
+        match self {
+            Location::Single(position) => {
+                let base = position.start();
+                if base == 0 || base > length {
+                    return Err(out_of_bounds(base, base));
+                }
+                extracted.push(sequence[base - 1]);
+            }
+            Location::Range(start, end) => {
+                let (start, end) = (start.start(), end.end());
+                if start == 0 || end == 0 || start > length || end > length {
+                    return Err(out_of_bounds(start, end));
+                }
+                if start <= end {
+                    extracted.extend_from_slice(&sequence[start - 1..end]);
+                } else if circular {
+                    extracted.extend_from_slice(&sequence[start - 1..]);
+                    extracted.extend_from_slice(&sequence[..end]);
+                } else {
+                    return Err(out_of_bounds(start, end));
+                }
+            }
+            // a site between two bases covers no sequence
+            Location::Between(..) => (),
+            Location::Complement(location) => {
+                let inner = location.extract(sequence, circular)?;
+                extracted.extend(inner.iter().rev().map(|&b| complement_base(b)));
+            }
+            Location::Join(locations) | Location::Order(locations) => {
+                for location in locations {
+                    location.extract_into(sequence, circular, extracted)?;
+                }
+            }
+            Location::Bond(_) => return Err(ExtractError::Unsupported("a bond location")),
+            Location::Gap(GapLength::Known(gap_length))
+            | Location::Gap(GapLength::Unknown(gap_length)) => {
+                // a gap can't be longer than the record it is in
+                if *gap_length > length {
+                    return Err(ExtractError::Unsupported("a gap longer than the sequence"));
+                }
+                extracted.extend(std::iter::repeat_n(b'n', *gap_length));
+            }
+            Location::Gap(GapLength::Unspecified) => {
+                return Err(ExtractError::Unsupported("a gap of unspecified length"))
+            }
+            Location::Remote { accession, .. } => {
+                return Err(ExtractError::RemoteLocation(accession.clone()))
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,199 @@
+use crate::{trim_ascii, FeatureType, Protein, Sequence};
+use std::fmt;
+use std::io::{self, Write};
+
+/// NCBI style defline of a nucleotide record, e.g. `AB000048.1 Feline panleukopenia virus ...`
+pub const DEFAULT_SEQUENCE_DEFLINE: &str = "{version} {definition}";
+/// NCBI style defline of a protein, e.g. `BAA19009.1 nonstructural protein 1 [Feline ...]`
+pub const DEFAULT_PROTEIN_DEFLINE: &str = "{protein_id} {product} [{organism}]";
+
+/// Error in a defline template.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeflineError {
+    UnknownField(String),
+    /// a `{` without the `}` closing it
+    UnclosedField,
+}
+
+impl fmt::Display for DeflineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DeflineError::UnknownField(name) => write!(f, "unknown defline field: {{{}}}", name),
+            DeflineError::UnclosedField => write!(f, "defline field is missing its closing }}"),
+        }
+    }
+}
+
+impl std::error::Error for DeflineError {}
+
+/// A value a defline template refers to as `{name}`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Accession,
+    Version,
+    Locus,
+    Definition,
+    Organism,
+    Taxonomy,
+    MolType,
+    Division,
+    /// number of residues written
+    Length,
+    ProteinId,
+    /// `/product` of a protein's CDS
+    Product,
+    /// `/gene` of a protein's CDS
+    Gene,
+    Location,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "accession" => Some(Self::Accession),
+            "version" => Some(Self::Version),
+            "locus" => Some(Self::Locus),
+            "definition" => Some(Self::Definition),
+            "organism" => Some(Self::Organism),
+            "taxonomy" => Some(Self::Taxonomy),
+            "mol_type" => Some(Self::MolType),
+            "division" => Some(Self::Division),
+            "length" => Some(Self::Length),
+            "protein_id" => Some(Self::ProteinId),
+            "product" => Some(Self::Product),
+            "gene" => Some(Self::Gene),
+            "location" => Some(Self::Location),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Text(String),
+    Field(Field),
+}
+
+/// Writes records as FASTA, with a defline filled in from a template and the residues wrapped
+/// at a fixed width.
+///
+/// Templates refer to record values as `{name}`: `accession`, `version`, `locus`,
+/// `definition`, `organism`, `taxonomy`, `mol_type`, `division` and `length`, and for proteins
+/// also `protein_id`, `product`, `gene` and `location`. Values a record doesn't have are left
+/// empty.
+#[derive(Debug, Clone)]
+pub struct Fasta {
+    defline: Vec<Part>,
+    line_width: usize,
+}
+
+impl Fasta {
+    /// `template` is the defline without its `>`, e.g. `{version} {definition} [{organism}]`.
+    pub fn new(template: &str) -> Result<Self, DeflineError> {
+        let mut defline = Vec::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                defline.push(Part::Text(rest[..start].to_string()));
+            }
+            let end = rest[start..].find('}').ok_or(DeflineError::UnclosedField)? + start;
+            let name = &rest[start + 1..end];
+            let field = Field::from_name(name)
+                .ok_or_else(|| DeflineError::UnknownField(name.to_string()))?;
+            defline.push(Part::Field(field));
+            rest = &rest[end + 1..];
+        }
+        if !rest.is_empty() {
+            defline.push(Part::Text(rest.to_string()));
+        }
+
+        Ok(Fasta {
+            defline,
+            line_width: 70,
+        })
+    }
+
+    /// Residues per line, 70 by default; 0 writes each sequence on a single line.
+    pub fn line_width(mut self, line_width: usize) -> Self {
+        self.line_width = line_width;
+        self
+    }
+
+    /// Writes the nucleotides, or residues of a protein record, of `sequence`.
+    pub fn write_sequence<W: Write>(&self, writer: W, sequence: &Sequence) -> io::Result<()> {
+        self.write_record(writer, sequence, None, &sequence.sequence)
+    }
+
+    /// Writes `protein`, one of the proteins of `sequence`.
+    pub fn write_protein<W: Write>(
+        &self,
+        writer: W,
+        protein: &Protein,
+        sequence: &Sequence,
+    ) -> io::Result<()> {
+        self.write_record(writer, sequence, Some(protein), &protein.sequence)
+    }
+
+    fn write_record<W: Write>(
+        &self,
+        mut writer: W,
+        sequence: &Sequence,
+        protein: Option<&Protein>,
+        residues: &[u8],
+    ) -> io::Result<()> {
+        // the CDS a protein was read or translated from
+        let cds = protein.and_then(|protein| {
+            sequence.features.iter().find(|feature| {
+                feature.feature_type == Some(FeatureType::Cds)
+                    && feature.location == protein.location
+            })
+        });
+
+        let mut defline = b">".to_vec();
+        for part in &self.defline {
+            let value = match part {
+                Part::Text(text) => {
+                    defline.extend_from_slice(text.as_bytes());
+                    continue;
+                }
+                Part::Field(Field::Length) => {
+                    defline.extend_from_slice(residues.len().to_string().as_bytes());
+                    continue;
+                }
+                Part::Field(Field::Accession) => &sequence.accession,
+                Part::Field(Field::Version) => &sequence.version,
+                Part::Field(Field::Locus) => &sequence.locus.name,
+                Part::Field(Field::Definition) => &sequence.definition,
+                Part::Field(Field::Organism) => &sequence.organism,
+                Part::Field(Field::Taxonomy) => &sequence.taxonomy,
+                Part::Field(Field::MolType) => &sequence.mol_type,
+                Part::Field(Field::Division) => &sequence.locus.division,
+                Part::Field(Field::ProteinId) => {
+                    protein.map_or(&[][..], |protein| &protein.protein_id)
+                }
+                Part::Field(Field::Location) => {
+                    protein.map_or(&[][..], |protein| &protein.location)
+                }
+                Part::Field(Field::Product) => cds
+                    .and_then(|cds| cds.qualifier(b"product"))
+                    .unwrap_or_default(),
+                Part::Field(Field::Gene) => cds
+                    .and_then(|cds| cds.qualifier(b"gene"))
+                    .unwrap_or_default(),
+            };
+            defline.extend_from_slice(trim_ascii(value));
+        }
+        writer.write_all(defline.trim_ascii_end())?;
+        writer.write_all(b"\n")?;
+
+        let line_width = match self.line_width {
+            0 => residues.len().max(1),
+            line_width => line_width,
+        };
+        for line in residues.chunks(line_width) {
+            writer.write_all(line)?;
+            writer.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+}
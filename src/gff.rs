@@ -0,0 +1,464 @@
+use crate::location::{Span, Strand};
+use crate::{Feature, FeatureType, Sequence};
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// First line of a GFF3 file.
+pub const GFF3_HEADER: &str = "##gff-version 3\n";
+/// Line between the features and the sequences of a GFF3 file; everything after it is FASTA.
+pub const GFF3_FASTA_DIRECTIVE: &str = "##FASTA\n";
+
+const SOURCE: &str = "GenBank";
+const RESIDUES_PER_LINE: usize = 60;
+
+/// Feature keys whose sequence ontology term is spelt differently.
+const SO_TERMS: [(&str, &str); 10] = [
+    ("source", "region"),
+    ("5'UTR", "five_prime_UTR"),
+    ("3'UTR", "three_prime_UTR"),
+    ("misc_feature", "sequence_feature"),
+    ("mat_peptide", "mature_protein_region_of_CDS"),
+    ("sig_peptide", "signal_peptide_region_of_CDS"),
+    ("precursor_RNA", "primary_transcript"),
+    ("prim_transcript", "primary_transcript"),
+    ("variation", "sequence_alteration"),
+    ("regulatory", "regulatory_region"),
+];
+
+/// Qualifiers with a GFF3 attribute of their own.
+const ATTRIBUTES: [(&[u8], &str); 2] = [(b"db_xref", "Dbxref"), (b"note", "Note")];
+
+/// How a feature takes part in the gene, transcript, CDS hierarchy.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Role {
+    Gene,
+    Transcript,
+    /// a CDS, exon, intron or UTR, part of a transcript or else of a gene
+    TranscriptPart,
+    Other,
+}
+
+impl Role {
+    fn of(feature_type: &FeatureType) -> Self {
+        match feature_type.as_str() {
+            "gene" => Role::Gene,
+            "mRNA" | "ncRNA" | "rRNA" | "tRNA" | "tmRNA" | "misc_RNA" | "precursor_RNA"
+            | "prim_transcript" => Role::Transcript,
+            "CDS" | "exon" | "intron" | "5'UTR" | "3'UTR" => Role::TranscriptPart,
+            _ => Role::Other,
+        }
+    }
+}
+
+/// A feature with what its GFF, GTF and BED lines need to refer to each other.
+pub(crate) struct Entry<'a> {
+    pub(crate) feature: &'a Feature,
+    pub(crate) feature_type: &'a FeatureType,
+    pub(crate) role: Role,
+    /// with the end of a span across the origin of a circular sequence past its length
+    pub(crate) spans: Vec<Span>,
+    /// `/locus_tag`, or else `/gene`, which ties genes, transcripts and CDSs together
+    pub(crate) key: Option<&'a [u8]>,
+    pub(crate) id: String,
+}
+
+impl Entry<'_> {
+    /// Lowest and highest base covered.
+    pub(crate) fn extent(&self) -> (usize, usize) {
+        let start = self.spans.iter().map(|span| span.start).min().unwrap_or(0);
+        let end = self.spans.iter().map(|span| span.end).max().unwrap_or(0);
+        (start, end)
+    }
+
+    /// `+` or `-`, or `.` for a location on both strands.
+    pub(crate) fn strand(&self) -> char {
+        if self.spans.iter().all(|span| span.strand == Strand::Forward) {
+            '+'
+        } else if self.spans.iter().all(|span| span.strand == Strand::Reverse) {
+            '-'
+        } else {
+            '.'
+        }
+    }
+
+    /// The number of bases before the first complete codon of each span of a CDS, from its
+    /// `/codon_start`, where anything other than 2 or 3 counts as 1.
+    fn phases(&self) -> Vec<usize> {
+        let codon_start = match self.feature.qualifier(b"codon_start") {
+            Some(b"2") => 2,
+            Some(b"3") => 3,
+            _ => 1,
+        };
+        // bases of the last codon read so far
+        let mut coding = 3 - (codon_start - 1) % 3;
+        self.spans
+            .iter()
+            .map(|span| {
+                let phase = (3 - coding % 3) % 3;
+                coding += span.end + 1 - span.start;
+                phase
+            })
+            .collect()
+    }
+}
+
+pub(crate) fn strand(span: &Span) -> char {
+    match span.strand {
+        Strand::Forward => '+',
+        Strand::Reverse => '-',
+    }
+}
+
+/// Identifier of a record in GFF and BED files: its VERSION, accession or LOCUS name.
+pub(crate) fn seqid(sequence: &Sequence) -> &[u8] {
+    [&sequence.version, &sequence.accession, &sequence.locus.name]
+        .into_iter()
+        .find(|id| !id.is_empty())
+        .map_or(&[][..], |id| id.as_slice())
+}
+
+/// Percent-encodes the bytes `keep` rejects, as GFF3 requires of seqids and attributes.
+fn escape(value: &[u8], keep: impl Fn(u8) -> bool) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for &b in value {
+        if keep(b) {
+            escaped.push(b as char);
+        } else {
+            escaped.push_str(&format!("%{:02X}", b));
+        }
+    }
+    escaped
+}
+
+pub(crate) fn escape_seqid(seqid: &[u8]) -> String {
+    escape(seqid, |b| {
+        b.is_ascii_alphanumeric() || b".:^*$@!+_?-|".contains(&b)
+    })
+}
+
+fn escape_attribute(value: &[u8]) -> String {
+    escape(value, |b| {
+        (b.is_ascii_graphic() || b == b' ') && !b";=&,%".contains(&b)
+    })
+}
+
+/// Writes the `##sequence-region` line and features of `sequence` as GFF3, after
+/// `GFF3_HEADER` and before `GFF3_FASTA_DIRECTIVE` if the sequences are written too.
+///
+/// Coordinates are 1-based and inclusive, and each stretch of a joined location is a line of
+/// its own, all sharing the feature's ID. Transcripts are linked to the gene and CDSs, exons,
+/// introns and UTRs to the transcript (or else the gene) with the same `/locus_tag`, or
+/// `/gene` if there is none. Features without a location on this record are left out, as are
+/// those reaching past its length or, on a linear record, across its origin.
+pub fn write_gff3<W: Write>(mut writer: W, sequence: &Sequence) -> io::Result<()> {
+    let seqid = escape_seqid(seqid(sequence));
+    writeln!(
+        writer,
+        "##sequence-region {} 1 {}",
+        seqid, sequence.locus.length
+    )?;
+
+    let entries = entries(sequence, &seqid);
+    for entry in &entries {
+        let parent = parent(entry, &entries);
+        let feature_type = entry.feature_type.as_str();
+        let gff_type = SO_TERMS
+            .iter()
+            .find(|(key, _)| *key == feature_type)
+            .map_or(feature_type, |(_, term)| term);
+        let attributes = attributes(entry, parent, sequence);
+
+        let phases = match entry.feature_type {
+            FeatureType::Cds => entry.phases().iter().map(ToString::to_string).collect(),
+            _ => vec![String::from("."); entry.spans.len()],
+        };
+        for (span, phase) in entry.spans.iter().zip(phases) {
+            writeln!(
+                writer,
+                "{}\t{}\t{}\t{}\t{}\t.\t{}\t{}\t{}",
+                seqid,
+                SOURCE,
+                gff_type,
+                span.start,
+                span.end,
+                strand(span),
+                phase,
+                attributes
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes the FASTA entry of `sequence` for the section after `GFF3_FASTA_DIRECTIVE`, named
+/// by the seqid of its features.
+pub fn write_gff3_sequence<W: Write>(mut writer: W, sequence: &Sequence) -> io::Result<()> {
+    writeln!(writer, ">{}", escape_seqid(seqid(sequence)))?;
+    for line in sequence.sequence.chunks(RESIDUES_PER_LINE) {
+        writer.write_all(line)?;
+        writer.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+/// Writes the gene models of `sequence` as GTF: genes, transcripts with an exon per stretch
+/// of their location, CDSs with their frame, and UTRs.
+///
+/// Every line has a `gene_id` and a `transcript_id`, empty for genes, taken from the GFF3 IDs
+/// of the gene and transcript it belongs to. A CDS outside any transcript, as in most bacterial
+/// and viral records, is its own transcript. Other features are left out.
+pub fn write_gtf<W: Write>(mut writer: W, sequence: &Sequence) -> io::Result<()> {
+    let seqid = escape_seqid(seqid(sequence));
+    let entries = entries(sequence, &seqid);
+    for entry in &entries {
+        let feature_type = entry.feature_type.as_str();
+        let gene = match entry.role {
+            Role::Gene => Some(entry),
+            _ => enclosing(entry, Role::Gene, &entries),
+        };
+        // features without a gene are a gene of their own, named by their key if they have one
+        let gene_id = match (gene, entry.key) {
+            (Some(gene), _) => gene.id.clone(),
+            (None, Some(key)) => format!("{}:gene-{}", seqid, escape_attribute(key)),
+            (None, None) => entry.id.clone(),
+        };
+        let transcript = match entry.role {
+            Role::Transcript => Some(entry),
+            Role::TranscriptPart => enclosing(entry, Role::Transcript, &entries),
+            _ => None,
+        };
+
+        let mut attributes = format!("gene_id \"{}\"; transcript_id \"", gene_id);
+        match (entry.role, transcript) {
+            (Role::Gene, _) => (),
+            (_, Some(transcript)) => attributes.push_str(&transcript.id),
+            _ if *entry.feature_type == FeatureType::Cds => attributes.push_str(&entry.id),
+            // exons, introns and UTRs outside a transcript
+            _ => continue,
+        }
+        attributes.push_str("\";");
+        let name = entry
+            .feature
+            .qualifier(b"gene")
+            .or_else(|| gene.and_then(|gene| gene.feature.qualifier(b"gene")));
+        for (attribute, value) in [
+            ("gene_name", name),
+            ("protein_id", entry.feature.qualifier(b"protein_id")),
+            ("product", entry.feature.qualifier(b"product")),
+        ] {
+            if let Some(value) = value {
+                attributes.push_str(&format!(" {} \"{}\";", attribute, gtf_value(value)));
+            }
+        }
+
+        let mut line = |gtf_type: &str, start: usize, end: usize, strand: char, frame: &str| {
+            writeln!(
+                writer,
+                "{}\t{}\t{}\t{}\t{}\t.\t{}\t{}\t{}",
+                seqid, SOURCE, gtf_type, start, end, strand, frame, attributes
+            )
+        };
+        let (start, end) = entry.extent();
+        match (entry.role, feature_type) {
+            (Role::Gene, _) => line("gene", start, end, entry.strand(), ".")?,
+            (Role::Transcript, _) => {
+                line("transcript", start, end, entry.strand(), ".")?;
+                for span in &entry.spans {
+                    line("exon", span.start, span.end, strand(span), ".")?;
+                }
+            }
+            (_, "CDS") => {
+                if transcript.is_none() {
+                    line("transcript", start, end, entry.strand(), ".")?;
+                    for span in &entry.spans {
+                        line("exon", span.start, span.end, strand(span), ".")?;
+                    }
+                }
+                for (span, phase) in entry.spans.iter().zip(entry.phases()) {
+                    line(
+                        "CDS",
+                        span.start,
+                        span.end,
+                        strand(span),
+                        &phase.to_string(),
+                    )?;
+                }
+            }
+            (_, "5'UTR" | "3'UTR") => {
+                let gtf_type = if feature_type == "5'UTR" {
+                    "5UTR"
+                } else {
+                    "3UTR"
+                };
+                for span in &entry.spans {
+                    line(gtf_type, span.start, span.end, strand(span), ".")?;
+                }
+            }
+            // exons and introns follow from the transcript
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+/// GTF values are quoted without a way to escape quotes, so they are replaced.
+fn gtf_value(value: &[u8]) -> String {
+    String::from_utf8_lossy(value).replace('"', "'")
+}
+
+/// The features of `sequence` that have a location on it, with unique IDs.
+pub(crate) fn entries<'a>(sequence: &'a Sequence, seqid: &str) -> Vec<Entry<'a>> {
+    let mut ids: HashMap<String, usize> = HashMap::new();
+    let mut entries = Vec::new();
+    for feature in &sequence.features {
+        let Some(feature_type) = &feature.feature_type else {
+            continue;
+        };
+        let spans = feature
+            .parsed_location()
+            .map(|location| location.spans())
+            .unwrap_or_default();
+        // positions are 1-based and within the record, and only a circular record has spans
+        // across its origin
+        let length = sequence.locus.length;
+        let on_record = |span: &Span| {
+            (1..=length).contains(&span.start)
+                && (1..=length).contains(&span.end)
+                && (span.start <= span.end || sequence.is_circular())
+        };
+        if spans.is_empty() || !spans.iter().all(on_record) {
+            continue;
+        }
+        let spans = spans
+            .into_iter()
+            .map(|span| {
+                if span.start > span.end {
+                    Span {
+                        end: span.end + length,
+                        ..span
+                    }
+                } else {
+                    span
+                }
+            })
+            .collect();
+
+        let role = Role::of(feature_type);
+        let key = feature
+            .qualifier(b"locus_tag")
+            .or_else(|| feature.qualifier(b"gene"));
+        let name = match role {
+            Role::Gene | Role::Transcript => key,
+            _ if *feature_type == FeatureType::Cds => feature.qualifier(b"protein_id").or(key),
+            _ => None,
+        };
+        let prefix = match role {
+            Role::Gene => "gene",
+            Role::Transcript => "rna",
+            _ if *feature_type == FeatureType::Cds => "cds",
+            _ => feature_type.as_str(),
+        };
+
+        // numbered when there is no name, or more than one feature with it
+        let base = match name {
+            Some(name) => format!("{}:{}-{}", seqid, prefix, escape_attribute(name)),
+            None => format!("{}:{}", seqid, prefix),
+        };
+        let count = ids.entry(base.clone()).or_default();
+        *count += 1;
+        let id = match (name, *count) {
+            (Some(_), 1) => base,
+            (_, count) => format!("{}-{}", base, count),
+        };
+
+        entries.push(Entry {
+            feature,
+            feature_type,
+            role,
+            spans,
+            key,
+            id,
+        });
+    }
+    entries
+}
+
+/// The first feature with `role` and the key of `entry` that covers it.
+pub(crate) fn enclosing<'a, 'b>(
+    entry: &Entry,
+    role: Role,
+    entries: &'b [Entry<'a>],
+) -> Option<&'b Entry<'a>> {
+    let key = entry.key?;
+    let (start, end) = entry.extent();
+    entries
+        .iter()
+        .filter(|other| other.role == role && other.key == Some(key))
+        .find(|other| {
+            let (other_start, other_end) = other.extent();
+            other_start <= start && end <= other_end
+        })
+}
+
+/// The ID of the feature `entry` is part of, if any.
+pub(crate) fn parent<'a>(entry: &Entry, entries: &'a [Entry]) -> Option<&'a str> {
+    let parent = match entry.role {
+        Role::Gene => None,
+        Role::Transcript | Role::Other => enclosing(entry, Role::Gene, entries),
+        Role::TranscriptPart => enclosing(entry, Role::Transcript, entries)
+            .or_else(|| enclosing(entry, Role::Gene, entries)),
+    };
+    parent.map(|parent| parent.id.as_str())
+}
+
+/// Column 9: the ID and Parent, a Name, the original key and the qualifiers, with repeated
+/// qualifiers joined into one multi-valued attribute. `/translation` is left out.
+fn attributes(entry: &Entry, parent: Option<&str>, sequence: &Sequence) -> String {
+    let mut attributes = vec![format!("ID={}", entry.id)];
+    if let Some(parent) = parent {
+        attributes.push(format!("Parent={}", parent));
+    }
+    let name = match entry.role {
+        Role::Gene => entry.feature.qualifier(b"gene").or(entry.key),
+        _ if *entry.feature_type == FeatureType::Cds => entry.feature.qualifier(b"protein_id"),
+        _ => None,
+    };
+    if let Some(name) = name {
+        attributes.push(format!("Name={}", escape_attribute(name)));
+    }
+    if *entry.feature_type == FeatureType::Source && sequence.is_circular() {
+        attributes.push(String::from("Is_circular=true"));
+    }
+    attributes.push(format!(
+        "gbkey={}",
+        escape_attribute(entry.feature_type.as_str().as_bytes())
+    ));
+
+    // qualifiers in the order they first appear, with the values of repeated ones together
+    let mut qualifiers: Vec<(&[u8], Vec<String>)> = Vec::new();
+    for (name, value) in &entry.feature.qualifiers {
+        if name == b"translation" {
+            continue;
+        }
+        let value = if value.is_empty() {
+            String::from("true")
+        } else {
+            escape_attribute(value)
+        };
+        match qualifiers.iter_mut().find(|(other, _)| other == name) {
+            Some((_, values)) => values.push(value),
+            None => qualifiers.push((name, vec![value])),
+        }
+    }
+    for (name, values) in qualifiers {
+        let name = ATTRIBUTES
+            .iter()
+            .find(|(qualifier, _)| *qualifier == name)
+            .map_or_else(
+                || escape_attribute(name),
+                |(_, attribute)| attribute.to_string(),
+            );
+        attributes.push(format!("{}={}", name, values.join(",")));
+    }
+    attributes.join(";")
+}
@@ -0,0 +1,636 @@
+use crate::{serialize_all_as_utf8, serialize_as_utf8, trim_ascii, DataType};
+use chrono::NaiveDate;
+use serde::{Serialize, Serializer};
+use std::fmt;
+
+#[inline]
+fn serialize_date<S>(date: &Option<NaiveDate>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match date {
+        Some(date) => serializer.collect_str(&date.format("%Y-%m-%d")),
+        None => serializer.serialize_none(),
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize)]
+pub enum LengthUnit {
+    #[default]
+    #[serde(rename = "bp")]
+    BasePairs,
+    #[serde(rename = "aa")]
+    AminoAcids,
+}
+
+impl LengthUnit {
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            b"bp" => Some(Self::BasePairs),
+            b"aa" => Some(Self::AminoAcids),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for LengthUnit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LengthUnit::BasePairs => write!(f, "bp"),
+            LengthUnit::AminoAcids => write!(f, "aa"),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Topology {
+    Linear,
+    Circular,
+}
+
+impl Topology {
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            b"linear" => Some(Self::Linear),
+            b"circular" => Some(Self::Circular),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Topology {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Topology::Linear => write!(f, "linear"),
+            Topology::Circular => write!(f, "circular"),
+        }
+    }
+}
+
+/// The LOCUS line, e.g.
+/// `LOCUS       AB000048                2007 bp    DNA     linear   VRL 14-JUL-2009`
+#[derive(Debug, Default, Serialize)]
+pub struct Locus {
+    #[serde(serialize_with = "serialize_as_utf8")]
+    pub name: Vec<u8>,
+    pub length: usize,
+    pub unit: LengthUnit,
+    /// e.g. `DNA`, `ss-RNA`, `mRNA`; empty when the line omits it
+    #[serde(serialize_with = "serialize_as_utf8")]
+    pub molecule_type: Vec<u8>,
+    pub topology: Option<Topology>,
+    /// three letter division code, e.g. `VRL`, `BCT`
+    #[serde(serialize_with = "serialize_as_utf8")]
+    pub division: Vec<u8>,
+    #[serde(serialize_with = "serialize_date")]
+    pub date: Option<NaiveDate>,
+}
+
+/// GenBank division codes, see section 3.3 of the release notes
+const DIVISIONS: [&[u8]; 20] = [
+    b"PRI", b"ROD", b"MAM", b"VRT", b"INV", b"PLN", b"BCT", b"VRL", b"PHG", b"SYN", b"UNA", b"EST",
+    b"PAT", b"STS", b"GSS", b"HTG", b"HTC", b"ENV", b"CON", b"TSA",
+];
+
+/// Parses the LOCUS line, returning a short reason on failure.
+///
+/// Tokens after the length are identified by their content rather than position, which copes
+/// with both the fixed-column layout and the older whitespace-separated lines that omit the
+/// topology (or molecule type). If the locus name has run into the length, the fixed columns
+/// are used instead. Only a missing name, length or unit is an error: a token after the
+/// molecule type that isn't otherwise recognised is taken as the division, and any further
+/// ones are ignored.
+pub(crate) fn parse_locus(line: &[u8]) -> Result<Locus, &'static str> {
+    // fixed columns (0-based, end exclusive)
+    const NAME_COLUMNS: std::ops::Range<usize> = 12..28;
+    const LENGTH_COLUMNS: std::ops::Range<usize> = 29..40;
+
+    let rest = line
+        .strip_prefix(b"LOCUS")
+        .ok_or("line does not start with LOCUS")?;
+    let mut tokens = rest
+        .split(|b| b.is_ascii_whitespace())
+        .filter(|token| !token.is_empty());
+
+    let name = tokens.next().ok_or("missing locus name")?;
+    let length = tokens.next().and_then(parse_usize);
+
+    let (name, length, tokens): (&[u8], usize, Vec<&[u8]>) = match length {
+        Some(length) => (name, length, tokens.collect()),
+        None => {
+            let column = |range: std::ops::Range<usize>| {
+                trim_ascii(
+                    line.get(range.start..range.end.min(line.len()))
+                        .unwrap_or_default(),
+                )
+            };
+            let length = parse_usize(column(LENGTH_COLUMNS)).ok_or("invalid sequence length")?;
+            let tokens = line
+                .get(LENGTH_COLUMNS.end..)
+                .unwrap_or_default()
+                .split(|b| b.is_ascii_whitespace())
+                .filter(|token| !token.is_empty())
+                .collect();
+            (column(NAME_COLUMNS), length, tokens)
+        }
+    };
+
+    let mut tokens = tokens.into_iter();
+    let mut locus = Locus {
+        name: name.to_vec(),
+        length,
+        unit: tokens
+            .next()
+            .and_then(LengthUnit::from_bytes)
+            .ok_or("missing length unit")?,
+        ..Locus::default()
+    };
+
+    for token in tokens {
+        if let Some(topology) = Topology::from_bytes(token) {
+            locus.topology = Some(topology);
+        } else if DIVISIONS.contains(&token) {
+            locus.division = token.to_vec();
+        } else if let Ok(date) =
+            NaiveDate::parse_from_str(&String::from_utf8_lossy(token), "%d-%b-%Y")
+        {
+            locus.date = Some(date);
+        } else if locus.molecule_type.is_empty() {
+            locus.molecule_type = token.to_vec();
+        } else if locus.division.is_empty() {
+            // a division no longer in use, e.g. `HUM` from before `PRI`, kept as written
+            locus.division = token.to_vec();
+        }
+    }
+
+    Ok(locus)
+}
+
+/// Splits the ACCESSION text into the primary accession and the secondary accessions.
+///
+/// Secondary accession ranges are expanded, and anything after a `REGION:` style keyword (as
+/// written by efetch for subsequences) is ignored.
+pub(crate) fn parse_accessions(data: &[u8]) -> (Vec<u8>, Vec<Vec<u8>>) {
+    let mut tokens = data
+        .split(|b| b.is_ascii_whitespace())
+        .filter(|token| !token.is_empty())
+        .take_while(|token| !token.ends_with(b":"));
+
+    let primary = tokens.next().unwrap_or_default().to_vec();
+    let secondary = tokens.flat_map(expand_accession_range).collect();
+
+    (primary, secondary)
+}
+
+/// Splits an accession into its prefix and trailing number, e.g. `AE` and `000111`.
+#[inline]
+fn split_number(accession: &[u8]) -> (&[u8], &[u8]) {
+    let digits_start = accession
+        .iter()
+        .rposition(|b| !b.is_ascii_digit())
+        .map_or(0, |pos| pos + 1);
+    accession.split_at(digits_start)
+}
+
+/// Largest number of accessions a secondary accession range is expanded into.
+const MAX_ACCESSION_RANGE: usize = 10_000;
+
+/// Expands `AE000111-AE000113` into `AE000111`, `AE000112`, `AE000113`.
+///
+/// Anything that is not a well formed range (same prefix, same number width, ascending) is
+/// returned unchanged as a single accession, as is a range of more than
+/// `MAX_ACCESSION_RANGE` accessions.
+fn expand_accession_range(token: &[u8]) -> Vec<Vec<u8>> {
+    let Some(dash) = token.iter().position(|&b| b == b'-') else {
+        return vec![token.to_vec()];
+    };
+    let (start_prefix, start_digits) = split_number(&token[..dash]);
+    let (end_prefix, end_digits) = split_number(&token[dash + 1..]);
+
+    let bounds = (parse_usize(start_digits), parse_usize(end_digits));
+    match bounds {
+        (Some(start), Some(end))
+            if start_prefix == end_prefix
+                && start_digits.len() == end_digits.len()
+                && start <= end
+                && end - start < MAX_ACCESSION_RANGE =>
+        {
+            let width = start_digits.len();
+            (start..=end)
+                .map(|number| {
+                    let mut accession = start_prefix.to_vec();
+                    accession.extend_from_slice(format!("{:0width$}", number).as_bytes());
+                    accession
+                })
+                .collect()
+        }
+        _ => vec![token.to_vec()],
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub enum DbLinkDatabase {
+    BioProject,
+    BioSample,
+    SequenceReadArchive,
+    Assembly,
+    TraceAssemblyArchive,
+    ProbeDb,
+    Other(String),
+}
+
+impl DbLinkDatabase {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        match bytes {
+            b"BioProject" => Self::BioProject,
+            b"BioSample" => Self::BioSample,
+            b"Sequence Read Archive" => Self::SequenceReadArchive,
+            b"Assembly" => Self::Assembly,
+            b"Trace Assembly Archive" => Self::TraceAssemblyArchive,
+            b"ProbeDB" => Self::ProbeDb,
+            _ => Self::Other(String::from_utf8_lossy(bytes).into_owned()),
+        }
+    }
+}
+
+impl fmt::Display for DbLinkDatabase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DbLinkDatabase::BioProject => write!(f, "BioProject"),
+            DbLinkDatabase::BioSample => write!(f, "BioSample"),
+            DbLinkDatabase::SequenceReadArchive => write!(f, "Sequence Read Archive"),
+            DbLinkDatabase::Assembly => write!(f, "Assembly"),
+            DbLinkDatabase::TraceAssemblyArchive => write!(f, "Trace Assembly Archive"),
+            DbLinkDatabase::ProbeDb => write!(f, "ProbeDB"),
+            DbLinkDatabase::Other(database) => write!(f, "{}", database),
+        }
+    }
+}
+
+/// One DBLINK entry, e.g. `Sequence Read Archive: SRR7694021, SRR7694022`
+#[derive(Debug, Serialize)]
+pub struct DbLink {
+    pub database: DbLinkDatabase,
+    #[serde(serialize_with = "serialize_all_as_utf8")]
+    pub ids: Vec<Vec<u8>>,
+}
+
+/// Header text that is only split up once the whole record has been read.
+#[derive(Default)]
+pub(crate) struct RawHeader {
+    pub(crate) references: Vec<RawReference>,
+    dblink: Vec<u8>,
+    keywords: Vec<u8>,
+    segment: Vec<u8>,
+}
+
+impl RawHeader {
+    pub(crate) fn append_data(&mut self, data_type: &DataType, data: &[u8]) {
+        match data_type {
+            DataType::DbLink => self.dblink.extend_from_slice(data),
+            DataType::Keywords => self.keywords.extend_from_slice(data),
+            DataType::Segment => self.segment.extend_from_slice(data),
+            _ => {
+                if let Some(reference) = self.references.last_mut() {
+                    reference.append_data(data_type, data);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn dblinks(&self) -> Vec<DbLink> {
+        let mut dblinks: Vec<DbLink> = Vec::new();
+        for line in self.dblink.split(|&b| b == b'\n') {
+            // a line without a database name continues the previous id list
+            let (database, ids) = match line.iter().position(|&b| b == b':') {
+                Some(pos) => (Some(trim_ascii(&line[..pos])), &line[pos + 1..]),
+                None => (None, line),
+            };
+            let ids = ids
+                .split(|&b| b == b',')
+                .map(trim_ascii)
+                .filter(|id| !id.is_empty())
+                .map(<[u8]>::to_vec);
+            match (database, dblinks.last_mut()) {
+                (Some(database), _) => dblinks.push(DbLink {
+                    database: DbLinkDatabase::from_bytes(database),
+                    ids: ids.collect(),
+                }),
+                (None, Some(last)) => last.ids.extend(ids),
+                (None, None) => (),
+            }
+        }
+        dblinks
+    }
+
+    /// `KEYWORDS    RefSeq; complete genome.`, where a lone `.` means no keywords
+    pub(crate) fn keywords(&self) -> Vec<Vec<u8>> {
+        let keywords = trim_ascii(&self.keywords);
+        let keywords = keywords.strip_suffix(b".").unwrap_or(keywords);
+        keywords
+            .split(|&b| b == b';')
+            .map(normalize_whitespace)
+            .filter(|keyword| !keyword.is_empty())
+            .collect()
+    }
+
+    /// `SEGMENT     1 of 8`
+    pub(crate) fn segment(&self) -> Option<(usize, usize)> {
+        let segment = normalize_whitespace(&self.segment);
+        let mut parts = crate::split_on_delimiter(&segment, b" of ", false).into_iter();
+        let number = parse_usize(parts.next()?)?;
+        let total = parse_usize(parts.next()?)?;
+        Some((number, total))
+    }
+}
+
+/// A REFERENCE block and its sub-keywords
+#[derive(Debug, Default, Serialize)]
+pub struct Reference {
+    pub number: usize,
+    /// inclusive, 1-based base (or residue) ranges the reference applies to
+    pub bases: Vec<(usize, usize)>,
+    #[serde(serialize_with = "serialize_all_as_utf8")]
+    pub authors: Vec<Vec<u8>>,
+    #[serde(serialize_with = "serialize_as_utf8")]
+    pub consortium: Vec<u8>,
+    #[serde(serialize_with = "serialize_as_utf8")]
+    pub title: Vec<u8>,
+    #[serde(serialize_with = "serialize_as_utf8")]
+    pub journal: Vec<u8>,
+    #[serde(serialize_with = "serialize_as_utf8")]
+    pub pubmed: Vec<u8>,
+    #[serde(serialize_with = "serialize_as_utf8")]
+    pub remark: Vec<u8>,
+}
+
+/// Text of a REFERENCE block collected line by line, before it is split up by `parse_reference`.
+#[derive(Default)]
+pub(crate) struct RawReference {
+    reference: Vec<u8>,
+    authors: Vec<u8>,
+    consortium: Vec<u8>,
+    title: Vec<u8>,
+    journal: Vec<u8>,
+    pubmed: Vec<u8>,
+    remark: Vec<u8>,
+}
+
+impl RawReference {
+    fn append_data(&mut self, data_type: &DataType, data: &[u8]) {
+        match data_type {
+            DataType::Reference => self.reference.extend_from_slice(data),
+            DataType::Authors => self.authors.extend_from_slice(data),
+            DataType::Consortium => self.consortium.extend_from_slice(data),
+            DataType::Title => self.title.extend_from_slice(data),
+            DataType::Journal => self.journal.extend_from_slice(data),
+            DataType::Pubmed => self.pubmed.extend_from_slice(data),
+            DataType::Remark => self.remark.extend_from_slice(data),
+            _ => (),
+        }
+    }
+}
+
+/// Collapses runs of whitespace (left by joining continuation lines) into single spaces.
+fn normalize_whitespace(data: &[u8]) -> Vec<u8> {
+    let mut normalized: Vec<u8> = data
+        .split(|b| b.is_ascii_whitespace())
+        .filter(|word| !word.is_empty())
+        .flat_map(|word| word.iter().chain(std::iter::once(&b' ')))
+        .copied()
+        .collect();
+    normalized.pop();
+    normalized
+}
+
+/// Splits `Gonin,P., Couillard,M. and d'Halewyn,M.A.` into the individual authors.
+fn split_authors(authors: &[u8]) -> Vec<Vec<u8>> {
+    let mut split: Vec<Vec<u8>> = crate::split_on_delimiter(authors, b", ", false)
+        .into_iter()
+        .map(<[u8]>::to_vec)
+        .collect();
+    if let Some(last) = split.pop() {
+        split.extend(
+            crate::split_on_delimiter(&last, b" and ", false)
+                .into_iter()
+                .map(<[u8]>::to_vec),
+        );
+    }
+    split
+}
+
+/// Parses `1  (bases 1 to 10; 20 to 30)` into the reference number and base ranges.
+///
+/// Ranges that can't be read, as in `(sites)`, are left out.
+fn parse_reference_line(data: &[u8]) -> (usize, Vec<(usize, usize)>) {
+    let mut parts = data.splitn(2, |&b| b == b'(');
+    let number = parts
+        .next()
+        .and_then(|number| parse_usize(trim_ascii(number)))
+        .unwrap_or(0);
+
+    let ranges = parts.next().unwrap_or_default();
+    let ranges = ranges.strip_suffix(b")").unwrap_or(ranges);
+    let ranges = ranges
+        .strip_prefix(b"bases")
+        .or_else(|| ranges.strip_prefix(b"residues"))
+        .unwrap_or(ranges);
+    let bases = ranges
+        .split(|&b| b == b';')
+        .filter_map(|range| {
+            let range = normalize_whitespace(range);
+            let mut bounds = crate::split_on_delimiter(&range, b" to ", false).into_iter();
+            let start = parse_usize(bounds.next()?)?;
+            let end = parse_usize(bounds.next()?)?;
+            Some((start, end))
+        })
+        .collect();
+
+    (number, bases)
+}
+
+pub(crate) fn parse_reference(raw: RawReference) -> Reference {
+    let (number, bases) = parse_reference_line(&normalize_whitespace(&raw.reference));
+
+    Reference {
+        number,
+        bases,
+        authors: split_authors(&normalize_whitespace(&raw.authors)),
+        consortium: normalize_whitespace(&raw.consortium),
+        title: normalize_whitespace(&raw.title),
+        journal: normalize_whitespace(&raw.journal),
+        pubmed: normalize_whitespace(&raw.pubmed),
+        remark: normalize_whitespace(&raw.remark),
+    }
+}
+
+/// An NCBI structured comment, e.g. the `##Genome-Assembly-Data-START##` block.
+#[derive(Debug, Default)]
+pub struct StructuredComment {
+    /// block name without the `-START##` marker, e.g. `Genome-Assembly-Data`
+    pub name: Vec<u8>,
+    /// key/value pairs in the order they appear
+    pub data: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+impl StructuredComment {
+    pub fn get(&self, key: &[u8]) -> Option<&[u8]> {
+        self.data
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_slice())
+    }
+}
+
+impl Serialize for StructuredComment {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        use serde::ser::{Error, SerializeMap, SerializeStruct};
+
+        struct Data<'a>(&'a [(Vec<u8>, Vec<u8>)]);
+
+        impl Serialize for Data<'_> {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                let mut map = serializer.serialize_map(Some(self.0.len()))?;
+                for (key, value) in self.0 {
+                    let key = std::str::from_utf8(key).map_err(S::Error::custom)?;
+                    let value = std::str::from_utf8(value).map_err(S::Error::custom)?;
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+        }
+
+        let name = std::str::from_utf8(&self.name).map_err(S::Error::custom)?;
+        let mut state = serializer.serialize_struct("StructuredComment", 2)?;
+        state.serialize_field("name", name)?;
+        state.serialize_field("data", &Data(&self.data))?;
+        state.end()
+    }
+}
+
+/// Finds the `##<name>-START##` ... `##<name>-END##` blocks in a COMMENT.
+///
+/// Inside a block each `key :: value` line starts a new entry, and lines without `::` continue
+/// the previous value.
+pub(crate) fn parse_structured_comments(comment: &[u8]) -> Vec<StructuredComment> {
+    let mut structured_comments = Vec::new();
+    let mut current: Option<StructuredComment> = None;
+
+    for line in comment.split(|&b| b == b'\n').map(trim_ascii) {
+        if let Some(name) = line
+            .strip_prefix(b"##")
+            .and_then(|line| line.strip_suffix(b"-START##"))
+        {
+            current = Some(StructuredComment {
+                name: name.to_vec(),
+                data: Vec::new(),
+            });
+        } else if line.starts_with(b"##") && line.ends_with(b"-END##") {
+            structured_comments.extend(current.take());
+        } else if let Some(structured_comment) = current.as_mut() {
+            match line.windows(2).position(|window| window == b"::") {
+                Some(pos) => structured_comment.data.push((
+                    trim_ascii(&line[..pos]).to_vec(),
+                    trim_ascii(&line[pos + 2..]).to_vec(),
+                )),
+                None if !line.is_empty() => {
+                    if let Some((_, last)) = structured_comment.data.last_mut() {
+                        last.push(b' ');
+                        last.extend_from_slice(line);
+                    }
+                }
+                None => (),
+            }
+        }
+    }
+
+    structured_comments
+}
+
+#[inline]
+fn parse_usize(bytes: &[u8]) -> Option<usize> {
+    std::str::from_utf8(bytes).ok()?.parse().ok()
+}
+
+/// The header at the top of an NCBI release file, e.g.
+///
+/// ```text
+/// GBBCT1.SEQ          Genetic Sequence Data Bank
+///                           October 15 2023
+///
+///                 NCBI-GenBank Flat File Release 258.0
+///
+///                      Bacterial Sequences (Part 1)
+///
+///    51396 loci,    92682287 bases, from    51396 reported sequences
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct ReleaseHeader {
+    #[serde(serialize_with = "serialize_as_utf8")]
+    pub file_name: Vec<u8>,
+    /// e.g. `258.0`
+    #[serde(serialize_with = "serialize_as_utf8")]
+    pub release: Vec<u8>,
+    #[serde(serialize_with = "serialize_date")]
+    pub date: Option<NaiveDate>,
+    /// the division title, e.g. `Bacterial Sequences (Part 1)`
+    #[serde(serialize_with = "serialize_as_utf8")]
+    pub title: Vec<u8>,
+    pub loci: usize,
+    pub bases: usize,
+    pub sequences: usize,
+}
+
+/// Parses the text before the first LOCUS line, or `None` if it isn't a release header.
+pub(crate) fn parse_release_header(data: &[u8]) -> Option<ReleaseHeader> {
+    let mut lines = data
+        .split(|&b| b == b'\n')
+        .map(trim_ascii)
+        .filter(|line| !line.is_empty());
+
+    let mut header = ReleaseHeader {
+        file_name: lines
+            .next()?
+            .split(|b| b.is_ascii_whitespace())
+            .next()?
+            .to_vec(),
+        ..ReleaseHeader::default()
+    };
+    let mut is_release = false;
+    for line in lines {
+        let words: Vec<&[u8]> = line
+            .split(|b| b.is_ascii_whitespace())
+            .filter(|word| !word.is_empty())
+            .collect();
+
+        if let Some(i) = words.iter().position(|&word| word == b"Release") {
+            is_release = true;
+            header.release = words.get(i + 1).unwrap_or(&&b""[..]).to_vec();
+        } else if words.contains(&&b"loci,"[..]) {
+            // `51396 loci,    92682287 bases, from    51396 reported sequences`
+            let count = |name: &[u8]| {
+                let i = words.iter().position(|&word| word.starts_with(name))?;
+                parse_usize(words.get(i.checked_sub(1)?)?)
+            };
+            header.loci = count(b"loci").unwrap_or_default();
+            header.bases = count(b"bases").unwrap_or_default();
+            header.sequences = count(b"reported").unwrap_or_default();
+        } else if let Ok(date) = NaiveDate::parse_from_str(
+            &String::from_utf8_lossy(&normalize_whitespace(line)),
+            "%B %d %Y",
+        ) {
+            header.date = Some(date);
+        } else if is_release && header.title.is_empty() {
+            header.title = normalize_whitespace(line);
+        }
+    }
+
+    is_release.then_some(header)
+}
@@ -1,6 +1,133 @@
 use serde::{ser::Error, Serialize, Serializer};
 use std::fmt;
 
+mod bed;
+mod embl;
+mod extract;
+mod fasta;
+mod gff;
+mod header;
+mod location;
+mod pipeline;
+mod reader;
+mod translation;
+mod validate;
+mod writer;
+
+pub use bed::{write_bed, write_bed12};
+pub use embl::parse_embl_record;
+pub use extract::{complement_base, reverse_complement, ExtractError};
+pub use fasta::{DeflineError, Fasta, DEFAULT_PROTEIN_DEFLINE, DEFAULT_SEQUENCE_DEFLINE};
+pub use gff::{write_gff3, write_gff3_sequence, write_gtf, GFF3_FASTA_DIRECTIVE, GFF3_HEADER};
+pub use header::{
+    DbLink, DbLinkDatabase, LengthUnit, Locus, Reference, ReleaseHeader, StructuredComment,
+    Topology,
+};
+pub use location::{GapLength, Location, LocationError, Position, Span, Strand};
+pub use pipeline::Pipeline;
+pub use reader::{decompress, GenbankReader, Input, Record};
+pub use translation::{
+    translate, GeneticCode, TranslationError, TranslationOptions, GENETIC_CODES,
+};
+pub use validate::{
+    validate_record, CdsReport, RecordIssue, ResidueMismatch, TranslationIssue, ValidationReport,
+};
+pub use writer::write_genbank;
+
+/// Errors raised while parsing a single GenBank or EMBL record.
+///
+/// Every variant carries the accession of the offending record (taken from the LOCUS line, or
+/// the ID line of an EMBL record, empty if even that could not be read) and, where it makes
+/// sense, the byte offset into the record at which the problem was found.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    LineTooShort {
+        accession: String,
+        offset: usize,
+    },
+    InvalidFeature {
+        accession: String,
+        offset: usize,
+        reason: &'static str,
+    },
+    InvalidUtf8 {
+        accession: String,
+        offset: usize,
+    },
+    /// the LOCUS line, or the ID line of an EMBL record, can't be read
+    InvalidLocus {
+        accession: String,
+        reason: &'static str,
+    },
+    /// the input ended, or another record started, before the `//` line ending this one
+    TruncatedRecord {
+        accession: String,
+    },
+    /// reading the input failed; `accession` is that of the record being read, if any
+    Io {
+        accession: String,
+        kind: std::io::ErrorKind,
+        message: String,
+    },
+}
+
+impl ParseError {
+    pub fn accession(&self) -> &str {
+        match self {
+            ParseError::LineTooShort { accession, .. }
+            | ParseError::InvalidFeature { accession, .. }
+            | ParseError::InvalidUtf8 { accession, .. }
+            | ParseError::InvalidLocus { accession, .. }
+            | ParseError::TruncatedRecord { accession }
+            | ParseError::Io { accession, .. } => accession,
+        }
+    }
+
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            ParseError::LineTooShort { offset, .. }
+            | ParseError::InvalidFeature { offset, .. }
+            | ParseError::InvalidUtf8 { offset, .. } => Some(*offset),
+            // the LOCUS line is always the first line of a record
+            ParseError::InvalidLocus { .. } => Some(0),
+            ParseError::TruncatedRecord { .. } | ParseError::Io { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::LineTooShort { accession, offset } => {
+                write!(f, "{}: line too short at byte {}", accession, offset)
+            }
+            ParseError::InvalidFeature {
+                accession,
+                offset,
+                reason,
+            } => write!(
+                f,
+                "{}: invalid feature at byte {}: {}",
+                accession, offset, reason
+            ),
+            ParseError::InvalidUtf8 { accession, offset } => {
+                write!(f, "{}: invalid UTF-8 at byte {}", accession, offset)
+            }
+            ParseError::InvalidLocus { accession, reason } => {
+                write!(f, "{}: invalid LOCUS line: {}", accession, reason)
+            }
+            ParseError::TruncatedRecord { accession } => {
+                write!(f, "{}: record ends without a // line", accession)
+            }
+            ParseError::Io {
+                accession, message, ..
+            } => write!(f, "{}: read failed: {}", accession, message),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
 #[inline]
 fn serialize_as_utf8<S>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error>
 where
@@ -10,10 +137,36 @@ where
     serializer.serialize_str(string)
 }
 
-#[derive(Debug, Serialize)]
+fn serialize_all_as_utf8<S>(items: &[Vec<u8>], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let strings = items
+        .iter()
+        .map(|bytes| std::str::from_utf8(bytes))
+        .collect::<Result<Vec<&str>, _>>()
+        .map_err(S::Error::custom)?;
+    serializer.collect_seq(strings)
+}
+
+#[derive(Debug, Default, Serialize)]
 pub struct Sequence {
+    pub locus: Locus,
+    /// primary accession
+    #[serde(serialize_with = "serialize_as_utf8")]
+    pub accession: Vec<u8>,
+    /// secondary accessions, with ranges such as `AE000111-AE000510` expanded
+    #[serde(serialize_with = "serialize_all_as_utf8")]
+    pub secondary_accessions: Vec<Vec<u8>>,
     #[serde(serialize_with = "serialize_as_utf8")]
     pub version: Vec<u8>,
+    pub dblinks: Vec<DbLink>,
+    #[serde(serialize_with = "serialize_as_utf8")]
+    pub project: Vec<u8>,
+    #[serde(serialize_with = "serialize_all_as_utf8")]
+    pub keywords: Vec<Vec<u8>>,
+    /// (segment number, total segments) from `SEGMENT     1 of 8`
+    pub segment: Option<(usize, usize)>,
     #[serde(serialize_with = "serialize_as_utf8")]
     pub definition: Vec<u8>,
     #[serde(serialize_with = "serialize_as_utf8")]
@@ -26,20 +179,55 @@ pub struct Sequence {
     pub host: Vec<u8>,
     #[serde(serialize_with = "serialize_as_utf8")]
     pub mol_type: Vec<u8>,
+    pub references: Vec<Reference>,
+    /// COMMENT text with its line breaks kept
+    #[serde(serialize_with = "serialize_as_utf8")]
+    pub comment: Vec<u8>,
+    /// `##...-START##` / `##...-END##` blocks found in the COMMENT
+    pub structured_comments: Vec<StructuredComment>,
+    /// the full feature table, in record order
+    pub features: Vec<Feature>,
 }
 
 impl Sequence {
+    pub fn is_circular(&self) -> bool {
+        self.locus.topology == Some(Topology::Circular)
+    }
+
+    /// Nucleotides covered by `feature`, spliced and reverse complemented as its location says.
+    pub fn feature_sequence(&self, feature: &Feature) -> Result<Vec<u8>, ExtractError> {
+        feature
+            .parsed_location()?
+            .extract(&self.sequence, self.is_circular())
+    }
+
     fn append_data(&mut self, data_type: &DataType, data: &[u8]) {
         match data_type {
             DataType::Definition => self.definition.extend_from_slice(data),
+            DataType::Accession => self.accession.extend_from_slice(data),
             DataType::Version => self.version.extend_from_slice(data),
+            DataType::Project => self.project.extend_from_slice(data),
             DataType::Taxonomy => self.taxonomy.extend_from_slice(data),
             DataType::Organism => self.organism.extend_from_slice(data),
+            DataType::Comment => self.comment.extend_from_slice(data),
             _ => (),
         }
     }
 }
 
+/// Nucleotide sequence of a CDS feature, the counterpart of a `Protein`.
+#[derive(Debug, Serialize)]
+pub struct CodingSequence {
+    #[serde(serialize_with = "serialize_as_utf8")]
+    pub protein_id: Vec<u8>,
+    #[serde(serialize_with = "serialize_as_utf8")]
+    pub source_id: Vec<u8>,
+    #[serde(serialize_with = "serialize_as_utf8")]
+    pub sequence: Vec<u8>,
+    #[serde(serialize_with = "serialize_as_utf8")]
+    pub location: Vec<u8>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct Protein {
     #[serde(serialize_with = "serialize_as_utf8")]
@@ -50,6 +238,9 @@ pub struct Protein {
     pub sequence: Vec<u8>,
     #[serde(serialize_with = "serialize_as_utf8")]
     pub location: Vec<u8>,
+    /// whether `sequence` was translated from the nucleotides rather than taken from the
+    /// `/translation` qualifier
+    pub translated: bool,
 }
 
 impl Protein {
@@ -59,15 +250,61 @@ impl Protein {
             source_id: Vec::new(),
             sequence: Vec::new(),
             location: Vec::new(),
+            translated: false,
         }
     }
 }
 
-#[derive(Debug)]
-struct Feature {
-    feature_type: Option<FeatureType>,
-    location: Vec<u8>,
-    qualifiers: Vec<(Vec<u8>, Vec<u8>)>,
+fn serialize_qualifiers<S>(
+    qualifiers: &[(Vec<u8>, Vec<u8>)],
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let pairs = qualifiers
+        .iter()
+        .map(|(name, value)| Ok((std::str::from_utf8(name)?, std::str::from_utf8(value)?)))
+        .collect::<Result<Vec<(&str, &str)>, std::str::Utf8Error>>()
+        .map_err(S::Error::custom)?;
+    serializer.collect_seq(pairs)
+}
+
+/// An entry of the feature table.
+///
+/// Qualifiers are kept in the order they appear, as `(name, value)` pairs with the `/` and any
+/// surrounding quotes removed; flag qualifiers such as `/pseudo` have an empty value.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Feature {
+    #[serde(rename = "key")]
+    pub feature_type: Option<FeatureType>,
+    #[serde(serialize_with = "serialize_as_utf8")]
+    pub location: Vec<u8>,
+    #[serde(serialize_with = "serialize_qualifiers")]
+    pub qualifiers: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+impl Feature {
+    /// Parses the raw location string, e.g. `join(complement(100..200),<1..>50)`.
+    pub fn parsed_location(&self) -> Result<Location, LocationError> {
+        Location::parse(&self.location)
+    }
+
+    /// Value of the first qualifier called `name`.
+    pub fn qualifier(&self, name: &[u8]) -> Option<&[u8]> {
+        self.qualifiers
+            .iter()
+            .find(|(qualifier_name, _)| qualifier_name == name)
+            .map(|(_, value)| value.as_slice())
+    }
+
+    /// Values of every qualifier called `name`, e.g. all `/db_xref`s.
+    pub fn qualifiers_named<'a>(&'a self, name: &'a [u8]) -> impl Iterator<Item = &'a [u8]> {
+        self.qualifiers
+            .iter()
+            .filter(move |(qualifier_name, _)| qualifier_name == name)
+            .map(|(_, value)| value.as_slice())
+    }
 }
 
 impl fmt::Display for Feature {
@@ -98,45 +335,236 @@ impl fmt::Display for Feature {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
-enum FeatureType {
-    Source,
+/// Feature keys of the INSDC feature table definition (v11.3).
+///
+/// Keys outside the vocabulary, including the legacy keys still found in older records (e.g.
+/// `promoter`, `LTR`), are kept as `Unknown`, so `Display` always writes back the original key.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FeatureType {
+    AssemblyGap,
+    CRegion,
+    Cds,
+    Centromere,
+    DLoop,
+    DSegment,
+    Exon,
+    Gap,
     Gene,
-    CDS,
-    Other,
+    IDna,
+    Intron,
+    JSegment,
+    MatPeptide,
+    MiscBinding,
+    MiscDifference,
+    MiscFeature,
+    MiscRecomb,
+    MiscRna,
+    MiscStructure,
+    MobileElement,
+    ModifiedBase,
+    MRna,
+    NcRna,
+    NRegion,
+    OldSequence,
+    Operon,
+    OriT,
+    PolyASite,
+    PrecursorRna,
+    PrimTranscript,
+    PrimerBind,
+    Propeptide,
+    ProteinBind,
+    Regulatory,
+    RepeatRegion,
+    RepOrigin,
+    RRna,
+    SRegion,
+    SigPeptide,
+    Source,
+    StemLoop,
+    Sts,
+    Telomere,
+    TmRna,
+    TransitPeptide,
+    TRna,
+    Unsure,
+    VRegion,
+    VSegment,
+    Variation,
+    ThreePrimeUtr,
+    FivePrimeUtr,
+    Unknown(String),
 }
 
 impl FeatureType {
     fn from_bytes(bytes: &[u8]) -> Option<Self> {
-        match trim_ascii(bytes) {
-            b"" => None,
-            b"source" => Some(Self::Source),
-            b"gene" => Some(Self::Gene),
-            b"CDS" => Some(Self::CDS),
-            _ => Some(Self::Other),
+        match std::str::from_utf8(trim_ascii(bytes)) {
+            Ok("") => None,
+            Ok(key) => key.parse().ok(),
+            Err(_) => Some(Self::Unknown(
+                String::from_utf8_lossy(trim_ascii(bytes)).into_owned(),
+            )),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            FeatureType::AssemblyGap => "assembly_gap",
+            FeatureType::CRegion => "C_region",
+            FeatureType::Cds => "CDS",
+            FeatureType::Centromere => "centromere",
+            FeatureType::DLoop => "D-loop",
+            FeatureType::DSegment => "D_segment",
+            FeatureType::Exon => "exon",
+            FeatureType::Gap => "gap",
+            FeatureType::Gene => "gene",
+            FeatureType::IDna => "iDNA",
+            FeatureType::Intron => "intron",
+            FeatureType::JSegment => "J_segment",
+            FeatureType::MatPeptide => "mat_peptide",
+            FeatureType::MiscBinding => "misc_binding",
+            FeatureType::MiscDifference => "misc_difference",
+            FeatureType::MiscFeature => "misc_feature",
+            FeatureType::MiscRecomb => "misc_recomb",
+            FeatureType::MiscRna => "misc_RNA",
+            FeatureType::MiscStructure => "misc_structure",
+            FeatureType::MobileElement => "mobile_element",
+            FeatureType::ModifiedBase => "modified_base",
+            FeatureType::MRna => "mRNA",
+            FeatureType::NcRna => "ncRNA",
+            FeatureType::NRegion => "N_region",
+            FeatureType::OldSequence => "old_sequence",
+            FeatureType::Operon => "operon",
+            FeatureType::OriT => "oriT",
+            FeatureType::PolyASite => "polyA_site",
+            FeatureType::PrecursorRna => "precursor_RNA",
+            FeatureType::PrimTranscript => "prim_transcript",
+            FeatureType::PrimerBind => "primer_bind",
+            FeatureType::Propeptide => "propeptide",
+            FeatureType::ProteinBind => "protein_bind",
+            FeatureType::Regulatory => "regulatory",
+            FeatureType::RepeatRegion => "repeat_region",
+            FeatureType::RepOrigin => "rep_origin",
+            FeatureType::RRna => "rRNA",
+            FeatureType::SRegion => "S_region",
+            FeatureType::SigPeptide => "sig_peptide",
+            FeatureType::Source => "source",
+            FeatureType::StemLoop => "stem_loop",
+            FeatureType::Sts => "STS",
+            FeatureType::Telomere => "telomere",
+            FeatureType::TmRna => "tmRNA",
+            FeatureType::TransitPeptide => "transit_peptide",
+            FeatureType::TRna => "tRNA",
+            FeatureType::Unsure => "unsure",
+            FeatureType::VRegion => "V_region",
+            FeatureType::VSegment => "V_segment",
+            FeatureType::Variation => "variation",
+            FeatureType::ThreePrimeUtr => "3'UTR",
+            FeatureType::FivePrimeUtr => "5'UTR",
+            FeatureType::Unknown(key) => key,
         }
     }
 }
 
+impl std::str::FromStr for FeatureType {
+    type Err = std::convert::Infallible;
+
+    fn from_str(key: &str) -> Result<Self, Self::Err> {
+        Ok(match key {
+            "assembly_gap" => Self::AssemblyGap,
+            "C_region" => Self::CRegion,
+            "CDS" => Self::Cds,
+            "centromere" => Self::Centromere,
+            "D-loop" => Self::DLoop,
+            "D_segment" => Self::DSegment,
+            "exon" => Self::Exon,
+            "gap" => Self::Gap,
+            "gene" => Self::Gene,
+            "iDNA" => Self::IDna,
+            "intron" => Self::Intron,
+            "J_segment" => Self::JSegment,
+            "mat_peptide" => Self::MatPeptide,
+            "misc_binding" => Self::MiscBinding,
+            "misc_difference" => Self::MiscDifference,
+            "misc_feature" => Self::MiscFeature,
+            "misc_recomb" => Self::MiscRecomb,
+            "misc_RNA" => Self::MiscRna,
+            "misc_structure" => Self::MiscStructure,
+            "mobile_element" => Self::MobileElement,
+            "modified_base" => Self::ModifiedBase,
+            "mRNA" => Self::MRna,
+            "ncRNA" => Self::NcRna,
+            "N_region" => Self::NRegion,
+            "old_sequence" => Self::OldSequence,
+            "operon" => Self::Operon,
+            "oriT" => Self::OriT,
+            "polyA_site" => Self::PolyASite,
+            "precursor_RNA" => Self::PrecursorRna,
+            "prim_transcript" => Self::PrimTranscript,
+            "primer_bind" => Self::PrimerBind,
+            "propeptide" => Self::Propeptide,
+            "protein_bind" => Self::ProteinBind,
+            "regulatory" => Self::Regulatory,
+            "repeat_region" => Self::RepeatRegion,
+            "rep_origin" => Self::RepOrigin,
+            "rRNA" => Self::RRna,
+            "S_region" => Self::SRegion,
+            "sig_peptide" => Self::SigPeptide,
+            "source" => Self::Source,
+            "stem_loop" => Self::StemLoop,
+            "STS" => Self::Sts,
+            "telomere" => Self::Telomere,
+            "tmRNA" => Self::TmRna,
+            "transit_peptide" => Self::TransitPeptide,
+            "tRNA" => Self::TRna,
+            "unsure" => Self::Unsure,
+            "V_region" => Self::VRegion,
+            "V_segment" => Self::VSegment,
+            "variation" => Self::Variation,
+            "3'UTR" => Self::ThreePrimeUtr,
+            "5'UTR" => Self::FivePrimeUtr,
+            _ => Self::Unknown(key.to_string()),
+        })
+    }
+}
+
+impl Serialize for FeatureType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
 impl fmt::Display for FeatureType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            FeatureType::Source => write!(f, "source"),
-            FeatureType::Gene => write!(f, "gene"),
-            FeatureType::CDS => write!(f, "CDS"),
-            FeatureType::Other => write!(f, "other"),
-        }
+        write!(f, "{}", self.as_str())
     }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 enum DataType {
+    Locus,
     Definition,
+    Accession,
     Version,
+    DbLink,
+    Project,
+    Keywords,
+    Segment,
     Organism,
     Origin,
     Source,
     Taxonomy,
+    Reference,
+    Authors,
+    Consortium,
+    Title,
+    Journal,
+    Pubmed,
+    Remark,
+    Comment,
     Features,
     Other,
 }
@@ -145,18 +573,51 @@ impl DataType {
     fn from_bytes(bytes: &[u8]) -> Option<Self> {
         match trim_ascii(bytes) {
             b"" => None,
+            b"LOCUS" => Some(Self::Locus),
             b"DEFINITION" => Some(Self::Definition),
+            b"ACCESSION" => Some(Self::Accession),
             b"VERSION" => Some(Self::Version),
+            b"DBLINK" => Some(Self::DbLink),
+            b"PROJECT" => Some(Self::Project),
+            b"KEYWORDS" => Some(Self::Keywords),
+            b"SEGMENT" => Some(Self::Segment),
             b"SOURCE" => Some(Self::Source),
             b"ORGANISM" => Some(Self::Organism),
             b"ORIGIN" => Some(Self::Origin),
+            b"REFERENCE" => Some(Self::Reference),
+            b"AUTHORS" => Some(Self::Authors),
+            b"CONSRTM" => Some(Self::Consortium),
+            b"TITLE" => Some(Self::Title),
+            b"JOURNAL" => Some(Self::Journal),
+            b"PUBMED" => Some(Self::Pubmed),
+            b"REMARK" => Some(Self::Remark),
+            b"COMMENT" => Some(Self::Comment),
             b"FEATURES" => Some(Self::Features),
             _ => Some(Self::Other),
         }
     }
 
+    /// Whether this is the REFERENCE line or one of its indented sub-keywords.
+    fn is_reference_data(&self) -> bool {
+        matches!(
+            self,
+            DataType::Reference
+                | DataType::Authors
+                | DataType::Consortium
+                | DataType::Title
+                | DataType::Journal
+                | DataType::Pubmed
+                | DataType::Remark
+        )
+    }
+
+    /// Whether continuation lines are significant and should be kept as separate lines.
+    fn keeps_line_breaks(&self) -> bool {
+        matches!(self, DataType::Comment | DataType::DbLink)
+    }
+
     fn is_data_complete(&self) -> bool {
-        matches!(self, DataType::Origin | DataType::Other) // Feature?
+        matches!(self, DataType::Locus | DataType::Origin | DataType::Other) // Feature?
     }
 }
 
@@ -202,50 +663,123 @@ pub fn split_on_delimiter<'a>(
     parts
 }
 
+#[inline]
+fn remove_whitespace(data: &[u8]) -> Vec<u8> {
+    data.iter()
+        .filter(|b| !b.is_ascii_whitespace())
+        .copied()
+        .collect()
+}
+
 #[inline]
 fn remove_quotes(data: &[u8]) -> &[u8] {
-    if data.starts_with(&[b'"']) && data.ends_with(&[b'"']) && data.len() >= 2 {
+    if data.starts_with(b"\"") && data.ends_with(b"\"") && data.len() >= 2 {
         &data[1..data.len() - 1]
     } else {
         data
     }
 }
 
-fn parse_features<'a, I>(data: I) -> Result<Vec<Feature>, &'static str>
+/// A residue of a sequence line: a letter, or the `*` ending some protein sequences.
+#[inline]
+fn is_residue(byte: &u8) -> bool {
+    byte.is_ascii_alphabetic() || *byte == b'*'
+}
+
+/// Byte offset of `line` within `record`; `line` must be a subslice of `record`.
+#[inline]
+fn offset_in(record: &[u8], line: &[u8]) -> usize {
+    line.as_ptr() as usize - record.as_ptr() as usize
+}
+
+/// Accession used to identify a record in errors: the first on its ACCESSION (or EMBL AC) line,
+/// else its VERSION, else the name on its LOCUS (or EMBL ID) line.
+fn record_accession(record: &[u8]) -> String {
+    let first_token = |data: &[u8]| {
+        data.split(|&b| b.is_ascii_whitespace() || b == b';')
+            .find(|token| !token.is_empty())
+            .map(<[u8]>::to_vec)
+    };
+    let mut lines = record.split(|&b| b == b'\n');
+    let first_line = lines.next().unwrap_or_default();
+    let (name, prefixes): (_, [&[u8]; 2]) = if embl::is_id_line(first_line) {
+        (first_line.strip_prefix(b"ID"), [b"AC ", b"SV "])
+    } else {
+        (
+            first_line.strip_prefix(b"LOCUS"),
+            [b"ACCESSION ", b"VERSION "],
+        )
+    };
+
+    // only the header, up to the feature table or sequence, holds these lines
+    let header: Vec<&[u8]> = lines
+        .take_while(|line| {
+            ![&b"FEATURES"[..], b"ORIGIN", b"FH ", b"FT ", b"SQ "]
+                .iter()
+                .any(|section| line.starts_with(section))
+        })
+        .collect();
+    let found = |prefix: &[u8]| {
+        header
+            .iter()
+            .filter_map(|line| line.strip_prefix(prefix))
+            .find_map(&first_token)
+    };
+    prefixes
+        .into_iter()
+        .find_map(found)
+        .or_else(|| name.and_then(first_token))
+        .map(|token| String::from_utf8_lossy(&token).into_owned())
+        .unwrap_or_default()
+}
+
+/// Column where feature locations and qualifiers start in a GenBank feature table.
+const FEATURE_QUALIFIER_INDENT: usize = 21;
+
+/// Parses the lines of a feature table whose locations and qualifiers start at column `indent`.
+fn parse_features<'a, I>(
+    data: I,
+    record: &[u8],
+    accession: &str,
+    indent: usize,
+) -> Result<Vec<Feature>, ParseError>
 where
     I: Iterator<Item = &'a [u8]>,
 {
-    const FEATURE_QUALIFIER_INDENT: usize = 21;
-    let feature_qualifier_spacer = [b' '; FEATURE_QUALIFIER_INDENT];
-    // should be same num spaces as FEATURE_QUALIFIER_INDENT
-    let feature_qualifier_delimeter = b"                     /";
+    let feature_qualifier_spacer = vec![b' '; indent];
+    let mut feature_qualifier_delimeter = feature_qualifier_spacer.clone();
+    feature_qualifier_delimeter.push(b'/');
     // println!("Data: {}", String::from_utf8_lossy(&data));
 
-    let feature_data =
-        data.filter(|&line| !line.is_empty())
-            .fold(Vec::new(), |mut acc: Vec<Vec<u8>>, line| {
-                if line.starts_with(&feature_qualifier_spacer) {
-                    if let Some(last) = acc.last_mut() {
-                        if line[FEATURE_QUALIFIER_INDENT] == b'/' {
-                            last.extend_from_slice(line);
-                        } else {
-                            // note: this might not be safe for all multiline qualifiers, but works for translations
-                            last.extend_from_slice(trim_ascii(line));
-                        }
+    // each entry is (offset of the feature's first line, joined feature lines)
+    let feature_data = data.filter(|&line| !line.is_empty()).fold(
+        Vec::new(),
+        |mut acc: Vec<(usize, Vec<u8>)>, line| {
+            if line.starts_with(&feature_qualifier_spacer) {
+                if let Some((_, last)) = acc.last_mut() {
+                    if line.get(indent) == Some(&b'/') {
+                        last.extend_from_slice(line);
                     } else {
-                        acc.push(line.to_vec());
+                        // continuation lines are joined with a space, which is removed again
+                        // from locations and translations below
+                        last.push(b' ');
+                        last.extend_from_slice(trim_ascii(line));
                     }
                 } else {
-                    acc.push(line.to_vec());
+                    acc.push((offset_in(record, line), line.to_vec()));
                 }
-                acc
-            });
+            } else {
+                acc.push((offset_in(record, line), line.to_vec()));
+            }
+            acc
+        },
+    );
 
     let mut features = Vec::new();
 
-    for feature_line in feature_data {
+    for (offset, feature_line) in feature_data {
         // let lines = feature_line.split(|&b| b == b'/');
-        let lines = split_on_delimiter(&feature_line, feature_qualifier_delimeter, false);
+        let lines = split_on_delimiter(&feature_line, &feature_qualifier_delimeter, false);
 
         let mut feature = Feature {
             feature_type: None,
@@ -256,26 +790,32 @@ where
         for (i, line) in lines.iter().enumerate() {
             // println!("Line ({}): {}", i, String::from_utf8_lossy(&line));
             if i == 0 {
-                if line.len() < FEATURE_QUALIFIER_INDENT {
-                    return Err("Line is too short for feature.");
+                if line.len() < indent {
+                    return Err(ParseError::InvalidFeature {
+                        accession: accession.to_string(),
+                        offset,
+                        reason: "line is too short for feature",
+                    });
                 }
-                feature.feature_type = FeatureType::from_bytes(&line[..FEATURE_QUALIFIER_INDENT]);
-                feature
-                    .location
-                    .extend_from_slice(trim_ascii(&line[FEATURE_QUALIFIER_INDENT..]));
+                feature.feature_type = FeatureType::from_bytes(&line[..indent]);
+                feature.location = remove_whitespace(&line[indent..]);
             } else {
-                let qualifier: Vec<&[u8]> = line.split(|&b| b == b'=').collect();
-                if qualifier.len() == 2 {
-                    let (qualifier_name, qualifier_value) =
-                        (qualifier[0], remove_quotes(qualifier[1]));
-                    feature
-                        .qualifiers
-                        .push((qualifier_name.to_vec(), qualifier_value.to_vec()));
-                } else {
-                    feature
-                        .qualifiers
-                        .push((qualifier[0].to_vec(), b"".to_vec()));
-                }
+                // values may themselves contain '=', so only split on the first one
+                let qualifier = match line.iter().position(|&b| b == b'=') {
+                    Some(pos) => {
+                        let name = &line[..pos];
+                        let value = remove_quotes(trim_ascii(&line[pos + 1..]));
+                        let value = if name == b"translation" {
+                            remove_whitespace(value)
+                        } else {
+                            // quotes inside a quoted value are escaped by doubling them
+                            split_on_delimiter(value, b"\"\"", false).join(&b'"')
+                        };
+                        (name.to_vec(), value)
+                    }
+                    None => (trim_ascii(line).to_vec(), Vec::new()),
+                };
+                feature.qualifiers.push(qualifier);
             }
         }
         // println!("Feature: {}", feature);
@@ -285,60 +825,110 @@ where
     Ok(features)
 }
 
-pub fn parse_sequence_record(record: &[u8]) -> (Sequence, Vec<Protein>) {
+/// Routes header text either to the sequence or, for fields that are split up once the whole
+/// record has been read, to the raw header.
+fn append_header_data(
+    sequence: &mut Sequence,
+    raw_header: &mut header::RawHeader,
+    data_type: &DataType,
+    data: &[u8],
+) {
+    match data_type {
+        DataType::DbLink | DataType::Keywords | DataType::Segment => {
+            raw_header.append_data(data_type, data)
+        }
+        data_type if data_type.is_reference_data() => raw_header.append_data(data_type, data),
+        _ => sequence.append_data(data_type, data),
+    }
+}
+
+pub fn parse_sequence_record(record: &[u8]) -> Result<(Sequence, Vec<Protein>), ParseError> {
     const GENBANK_INDENT: usize = 12;
     // let genbank_spacer = [b' '; GENBANK_INDENT];
 
-    let mut sequence = Sequence {
-        version: Vec::new(),
-        definition: Vec::new(),
-        taxonomy: Vec::new(),
-        organism: Vec::new(),
-        sequence: Vec::new(),
-        host: Vec::new(),
-        mol_type: Vec::new(),
-    };
-    let mut origin = Vec::new();
+    let accession = record_accession(record);
+    if let Err(err) = std::str::from_utf8(record) {
+        return Err(ParseError::InvalidUtf8 {
+            accession,
+            offset: err.valid_up_to(),
+        });
+    }
+
+    let mut sequence = Sequence::default();
     let mut features = Vec::new();
 
-    let mut lines = record.split(|&b| b == b'\n');
+    let mut lines = record.split(|&b| b == b'\n').peekable();
+
+    let mut raw_header = header::RawHeader::default();
 
     let mut data_type: DataType = DataType::Other;
-    for line in &mut lines {
-        if line.len() < GENBANK_INDENT {
-            panic!(
-                "Line is too short for sequence: {:?}",
-                String::from_utf8(sequence.version.clone())
-            );
+    while let Some(line) = lines.next() {
+        // records straight from a file may still end with their `//` line
+        if line.trim_ascii_end() == b"//" {
+            break;
+        }
+        // blank lines, e.g. before FEATURES, are skipped, but separate COMMENT paragraphs
+        if trim_ascii(line).is_empty() {
+            if data_type == DataType::Comment {
+                sequence.append_data(&data_type, b"\n");
+            }
+            continue;
+        }
+        // a bare ORIGIN line, without the padding to the indent, is common enough to allow
+        if line.len() < GENBANK_INDENT && !line.starts_with(b"ORIGIN") {
+            return Err(ParseError::LineTooShort {
+                accession,
+                offset: offset_in(record, line),
+            });
         }
 
-        let line_type = DataType::from_bytes(&line[..GENBANK_INDENT]);
+        let line_type = DataType::from_bytes(&line[..GENBANK_INDENT.min(line.len())]);
         // println!("line_type: {:?}", line_type);
         match line_type {
             None => {
                 if !data_type.is_data_complete() {
-                    sequence.append_data(&data_type, &[b' ']);
+                    let separator: &[u8] = if data_type.keeps_line_breaks() {
+                        b"\n"
+                    } else {
+                        b" "
+                    };
+                    append_header_data(&mut sequence, &mut raw_header, &data_type, separator);
                 }
             }
             Some(DataType::Features) => {
-                // features followed by origin
-                let feature_lines = lines
+                // the table runs until the next section, whose lines aren't indented
+                let feature_lines = std::iter::from_fn(|| {
+                    lines.next_if(|line| line.is_empty() || line[0].is_ascii_whitespace())
+                });
+                features =
+                    parse_features(feature_lines, record, &accession, FEATURE_QUALIFIER_INDENT)?;
+                data_type = DataType::Other;
+                continue;
+            }
+            Some(DataType::Origin) => {
+                // the sequence is the last section of a record
+                sequence.sequence = lines
                     .by_ref()
-                    .take_while(|slice| !slice.starts_with(b"ORIGIN")); // ORIGIN line consumed here?
-                features = parse_features(feature_lines).expect(&format!(
-                    "Failed to parse features for sequence: {:?}",
-                    String::from_utf8(sequence.version.clone())
-                ));
-
-                origin = lines
-                    .flat_map(|slice| slice)
-                    .filter(|&&b| b.is_ascii_alphabetic())
+                    .flatten()
+                    .filter(|&b| is_residue(b))
                     .copied()
                     .collect();
                 break;
             }
             Some(d_type) => {
                 data_type = d_type;
+                match data_type {
+                    DataType::Locus => {
+                        sequence.locus = header::parse_locus(line).map_err(|reason| {
+                            ParseError::InvalidLocus {
+                                accession: accession.clone(),
+                                reason,
+                            }
+                        })?;
+                    }
+                    DataType::Reference => raw_header.references.push(Default::default()),
+                    _ => (),
+                }
             }
         }
 
@@ -347,42 +937,84 @@ pub fn parse_sequence_record(record: &[u8]) -> (Sequence, Vec<Protein>) {
         }
 
         if !data_type.is_data_complete() {
-            sequence.append_data(&data_type, &line[GENBANK_INDENT..]);
+            append_header_data(
+                &mut sequence,
+                &mut raw_header,
+                &data_type,
+                &line[GENBANK_INDENT..],
+            );
         }
     }
 
-    // todo: handle features
+    Ok(finish_record(sequence, raw_header, features))
+}
+
+/// Splits up the header text collected while reading a record, and takes the host, molecule
+/// type and proteins from its features.
+fn finish_record(
+    mut sequence: Sequence,
+    raw_header: header::RawHeader,
+    features: Vec<Feature>,
+) -> (Sequence, Vec<Protein>) {
+    // the raw ACCESSION text is collected first, then split into primary and secondaries
+    let (primary_accession, secondary_accessions) = header::parse_accessions(&sequence.accession);
+    sequence.accession = primary_accession;
+    sequence.secondary_accessions = secondary_accessions;
+    sequence.project = trim_ascii(&sequence.project).to_vec();
+    sequence.dblinks = raw_header.dblinks();
+    sequence.keywords = raw_header.keywords();
+    sequence.segment = raw_header.segment();
+    sequence.references = raw_header
+        .references
+        .into_iter()
+        .map(header::parse_reference)
+        .collect();
+    while sequence.comment.last().is_some_and(u8::is_ascii_whitespace) {
+        sequence.comment.pop();
+    }
+    sequence.structured_comments = header::parse_structured_comments(&sequence.comment);
+
     let mut proteins = Vec::new();
-    for feature in features {
+    for feature in &features {
         match feature.feature_type {
             Some(FeatureType::Source) => {
-                for (qualifier_name, qualifier_value) in feature.qualifiers {
+                for (qualifier_name, qualifier_value) in &feature.qualifiers {
                     match qualifier_name.as_slice() {
                         b"host" | b"lab_host" => {
-                            sequence.host = qualifier_value;
+                            sequence.host = qualifier_value.clone();
                         }
                         b"mol_type" => {
-                            sequence.mol_type = qualifier_value;
+                            sequence.mol_type = qualifier_value.clone();
                         }
                         _ => (),
                     }
                 }
             }
-            Some(FeatureType::CDS) => {
+            Some(FeatureType::Cds) => {
                 let mut protein = Protein::new();
-                for (qualifier_name, qualifier_value) in feature.qualifiers {
+                for (qualifier_name, qualifier_value) in &feature.qualifiers {
                     match qualifier_name.as_slice() {
                         b"protein_id" => {
-                            protein.protein_id = qualifier_value;
+                            protein.protein_id = qualifier_value.clone();
                         }
                         b"translation" => {
-                            protein.sequence = qualifier_value;
+                            protein.sequence = qualifier_value.clone();
                         }
                         _ => (),
                     }
                 }
+                // CDSs without a /translation (partial CDSs, ...) are translated, but not
+                // pseudo genes, whose stops and frameshifts would come out as a protein
+                let pseudo = feature.qualifier(b"pseudo").is_some()
+                    || feature.qualifier(b"pseudogene").is_some();
+                if protein.sequence.is_empty() && !pseudo {
+                    if let Ok(translation) = sequence.translate_feature(feature) {
+                        protein.sequence = translation;
+                        protein.translated = true;
+                    }
+                }
                 if !protein.protein_id.is_empty() && !protein.sequence.is_empty() {
-                    protein.location = feature.location;
+                    protein.location = feature.location.clone();
                     protein.source_id = sequence.version.clone();
                     proteins.push(protein);
                 }
@@ -390,12 +1022,21 @@ pub fn parse_sequence_record(record: &[u8]) -> (Sequence, Vec<Protein>) {
             _ => (),
         }
     }
-    sequence.sequence = origin;
+    sequence.features = features;
     // println!("Num proteins: {}", proteins.len());
 
     (sequence, proteins)
 }
 
+/// Parses a GenBank record, or an EMBL record if it starts with an `ID` line.
+pub fn parse_record(record: &[u8]) -> Result<(Sequence, Vec<Protein>), ParseError> {
+    if embl::is_id_line(record) {
+        parse_embl_record(record)
+    } else {
+        parse_sequence_record(record)
+    }
+}
+
 pub fn parse_sequence_record_by_positions(record: &[u8]) -> Sequence {
     let definition_start = record
         .windows(11)
@@ -476,7 +1117,7 @@ pub fn parse_sequence_record_by_positions(record: &[u8]) -> Sequence {
 
     let sequence = record[origin_start..]
         .iter()
-        .filter(|&&b| b.is_ascii_alphabetic())
+        .filter(|&b| is_residue(b))
         .copied()
         .collect();
 
@@ -492,5 +1133,6 @@ pub fn parse_sequence_record_by_positions(record: &[u8]) -> Sequence {
         sequence,
         host,
         mol_type,
+        ..Sequence::default()
     }
 }
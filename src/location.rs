@@ -0,0 +1,480 @@
+use std::fmt;
+
+/// Error raised while parsing a feature location.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocationError {
+    /// byte offset into the location string
+    pub offset: usize,
+    pub reason: &'static str,
+}
+
+impl fmt::Display for LocationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "invalid location at byte {}: {}",
+            self.offset, self.reason
+        )
+    }
+}
+
+impl std::error::Error for LocationError {}
+
+/// A 1-based base position.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Position {
+    /// `100`
+    Exact(usize),
+    /// `<100`, the feature extends beyond this position
+    Before(usize),
+    /// `>100`
+    After(usize),
+    /// `(102.110)`, a single base somewhere in this range
+    Within(usize, usize),
+}
+
+impl Position {
+    /// Lowest base the position may refer to.
+    pub fn start(&self) -> usize {
+        match *self {
+            Position::Exact(position) | Position::Before(position) | Position::After(position) => {
+                position
+            }
+            Position::Within(start, _) => start,
+        }
+    }
+
+    /// Highest base the position may refer to.
+    pub fn end(&self) -> usize {
+        match *self {
+            Position::Within(_, end) => end,
+            position => position.start(),
+        }
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Position::Exact(position) => write!(f, "{}", position),
+            Position::Before(position) => write!(f, "<{}", position),
+            Position::After(position) => write!(f, ">{}", position),
+            Position::Within(start, end) => write!(f, "({}.{})", start, end),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Strand {
+    Forward,
+    Reverse,
+}
+
+impl Strand {
+    fn flip(self) -> Self {
+        match self {
+            Strand::Forward => Strand::Reverse,
+            Strand::Reverse => Strand::Forward,
+        }
+    }
+}
+
+/// A contiguous stretch of a location, 1-based and inclusive.
+///
+/// `start <= end` except for a range wrapping around the origin of a circular sequence.
+/// `partial_start`/`partial_end` mark a `<` on `start` or a `>` on `end`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub strand: Strand,
+    pub partial_start: bool,
+    pub partial_end: bool,
+}
+
+impl Span {
+    /// Whether the 5' end of the span, taking the strand into account, is partial.
+    fn is_five_prime_partial(&self) -> bool {
+        match self.strand {
+            Strand::Forward => self.partial_start,
+            Strand::Reverse => self.partial_end,
+        }
+    }
+
+    fn is_three_prime_partial(&self) -> bool {
+        match self.strand {
+            Strand::Forward => self.partial_end,
+            Strand::Reverse => self.partial_start,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GapLength {
+    /// `gap()`
+    Unspecified,
+    /// `gap(100)`
+    Known(usize),
+    /// `gap(unk100)`
+    Unknown(usize),
+}
+
+/// A feature location, following the INSDC location grammar.
+///
+/// `Display` writes the canonical location string back out, e.g.
+/// `join(complement(100..200),<1..>50)`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Location {
+    /// `467`
+    Single(Position),
+    /// `340..565`, `<1..>888`
+    Range(Position, Position),
+    /// `123^124`, a site between two bases
+    Between(usize, usize),
+    Complement(Box<Location>),
+    Join(Vec<Location>),
+    Order(Vec<Location>),
+    Bond(Vec<Location>),
+    Gap(GapLength),
+    /// `J00194.1:100..202`, a location on another record
+    Remote {
+        accession: String,
+        location: Box<Location>,
+    },
+}
+
+impl Location {
+    pub fn parse(location: &[u8]) -> Result<Self, LocationError> {
+        let mut parser = Parser {
+            data: location,
+            offset: 0,
+        };
+        let location = parser.parse_location()?;
+        parser.skip_whitespace();
+        if parser.offset < parser.data.len() {
+            return Err(parser.error("unexpected trailing characters"));
+        }
+        Ok(location)
+    }
+}
+
+impl Location {
+    /// Contiguous stretches covered by this location in reading order, i.e. 5' to 3' on the
+    /// strand of each stretch. Gaps, between-base sites and remote parts are left out.
+    pub fn spans(&self) -> Vec<Span> {
+        let mut spans = Vec::new();
+        self.collect_spans(&mut spans);
+        spans
+    }
+
+    fn collect_spans(&self, spans: &mut Vec<Span>) {
+        match self {
+            Location::Single(position) => spans.push(Span {
+                start: position.start(),
+                end: position.end(),
+                strand: Strand::Forward,
+                partial_start: matches!(position, Position::Before(_)),
+                partial_end: matches!(position, Position::After(_)),
+            }),
+            Location::Range(start, end) => spans.push(Span {
+                start: start.start(),
+                end: end.end(),
+                strand: Strand::Forward,
+                partial_start: matches!(start, Position::Before(_)),
+                partial_end: matches!(end, Position::After(_)),
+            }),
+            Location::Complement(location) => {
+                let mut inner = location.spans();
+                inner.reverse();
+                spans.extend(inner.into_iter().map(|span| Span {
+                    strand: span.strand.flip(),
+                    ..span
+                }));
+            }
+            Location::Join(locations) | Location::Order(locations) | Location::Bond(locations) => {
+                for location in locations {
+                    location.collect_spans(spans);
+                }
+            }
+            Location::Between(..) | Location::Gap(_) | Location::Remote { .. } => (),
+        }
+    }
+
+    /// `Reverse` only if every stretch of the location is on the reverse strand.
+    pub fn strand(&self) -> Strand {
+        let spans = self.spans();
+        if !spans.is_empty() && spans.iter().all(|span| span.strand == Strand::Reverse) {
+            Strand::Reverse
+        } else {
+            Strand::Forward
+        }
+    }
+
+    /// Whether the 5' end of the location is partial, e.g. `<1..100` or `complement(1..>100)`.
+    pub fn is_five_prime_partial(&self) -> bool {
+        self.spans()
+            .first()
+            .is_some_and(Span::is_five_prime_partial)
+    }
+
+    pub fn is_three_prime_partial(&self) -> bool {
+        self.spans()
+            .last()
+            .is_some_and(Span::is_three_prime_partial)
+    }
+
+    /// Offset of the 1-based `position` from the start of the bases covered by this location,
+    /// in reading order, or `None` if the location doesn't cover it.
+    ///
+    /// A stretch whose start is after its end wraps around the origin of a circular sequence of
+    /// `length` bases, e.g. `4500..100`.
+    pub fn offset_of(&self, position: usize, length: usize) -> Option<usize> {
+        let mut offset = 0;
+        for span in self.spans() {
+            let wraps = span.start > span.end;
+            let covered = if wraps {
+                position >= span.start || position <= span.end
+            } else {
+                (span.start..=span.end).contains(&position)
+            };
+            if covered {
+                // positions past the origin are counted as if it weren't there
+                let unwrapped = |position: usize| {
+                    if wraps && position <= span.end {
+                        position + length
+                    } else {
+                        position
+                    }
+                };
+                return Some(match span.strand {
+                    Strand::Forward => offset + unwrapped(position) - span.start,
+                    Strand::Reverse => offset + unwrapped(span.end) - unwrapped(position),
+                });
+            }
+            offset += if wraps {
+                (length + 1).saturating_sub(span.start) + span.end
+            } else {
+                span.end - span.start + 1
+            };
+        }
+        None
+    }
+}
+
+impl std::str::FromStr for Location {
+    type Err = LocationError;
+
+    fn from_str(location: &str) -> Result<Self, Self::Err> {
+        Location::parse(location.as_bytes())
+    }
+}
+
+fn write_list(f: &mut fmt::Formatter, name: &str, locations: &[Location]) -> fmt::Result {
+    write!(f, "{}(", name)?;
+    for (i, location) in locations.iter().enumerate() {
+        if i > 0 {
+            write!(f, ",")?;
+        }
+        write!(f, "{}", location)?;
+    }
+    write!(f, ")")
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            // a lone within position is written without parentheses
+            Location::Single(Position::Within(start, end)) => write!(f, "{}.{}", start, end),
+            Location::Single(position) => write!(f, "{}", position),
+            Location::Range(start, end) => write!(f, "{}..{}", start, end),
+            Location::Between(start, end) => write!(f, "{}^{}", start, end),
+            Location::Complement(location) => write!(f, "complement({})", location),
+            Location::Join(locations) => write_list(f, "join", locations),
+            Location::Order(locations) => write_list(f, "order", locations),
+            Location::Bond(locations) => write_list(f, "bond", locations),
+            Location::Gap(GapLength::Unspecified) => write!(f, "gap()"),
+            Location::Gap(GapLength::Known(length)) => write!(f, "gap({})", length),
+            Location::Gap(GapLength::Unknown(length)) => write!(f, "gap(unk{})", length),
+            Location::Remote {
+                accession,
+                location,
+            } => write!(f, "{}:{}", accession, location),
+        }
+    }
+}
+
+/// Recursive descent parser over the location bytes.
+struct Parser<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, reason: &'static str) -> LocationError {
+        LocationError {
+            offset: self.offset,
+            reason,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self
+            .data
+            .get(self.offset)
+            .is_some_and(u8::is_ascii_whitespace)
+        {
+            self.offset += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.data.get(self.offset).copied()
+    }
+
+    fn eat(&mut self, token: &[u8]) -> bool {
+        self.skip_whitespace();
+        if self.data[self.offset..].starts_with(token) {
+            self.offset += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &[u8], reason: &'static str) -> Result<(), LocationError> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.error(reason))
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<usize, LocationError> {
+        self.skip_whitespace();
+        let digits = self.data[self.offset..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        if digits == 0 {
+            return Err(self.error("expected a number"));
+        }
+        let number = std::str::from_utf8(&self.data[self.offset..self.offset + digits])
+            .ok()
+            .and_then(|digits| digits.parse().ok())
+            .ok_or_else(|| self.error("number is too large"))?;
+        self.offset += digits;
+        Ok(number)
+    }
+
+    fn parse_location(&mut self) -> Result<Location, LocationError> {
+        self.skip_whitespace();
+        let rest = &self.data[self.offset..];
+        let name_length = rest
+            .iter()
+            .take_while(|b| b.is_ascii_alphanumeric() || **b == b'_' || **b == b'.')
+            .count();
+        let name = &rest[..name_length];
+
+        match (name, rest.get(name_length)) {
+            (b"complement", Some(b'(')) => {
+                self.offset += name_length + 1;
+                let location = self.parse_location()?;
+                self.expect(b")", "expected ')' to close complement")?;
+                Ok(Location::Complement(Box::new(location)))
+            }
+            (b"join", Some(b'(')) => {
+                self.offset += name_length + 1;
+                Ok(Location::Join(self.parse_list()?))
+            }
+            (b"order", Some(b'(')) => {
+                self.offset += name_length + 1;
+                Ok(Location::Order(self.parse_list()?))
+            }
+            (b"bond", Some(b'(')) => {
+                self.offset += name_length + 1;
+                Ok(Location::Bond(self.parse_list()?))
+            }
+            (b"gap", Some(b'(')) => {
+                self.offset += name_length + 1;
+                let gap = if self.eat(b")") {
+                    return Ok(Location::Gap(GapLength::Unspecified));
+                } else if self.eat(b"unk") {
+                    GapLength::Unknown(self.parse_number()?)
+                } else {
+                    GapLength::Known(self.parse_number()?)
+                };
+                self.expect(b")", "expected ')' to close gap")?;
+                Ok(Location::Gap(gap))
+            }
+            (name, Some(b':')) if name.first().is_some_and(u8::is_ascii_alphabetic) => {
+                self.offset += name_length + 1;
+                let accession = String::from_utf8_lossy(name).into_owned();
+                let location = self.parse_location()?;
+                Ok(Location::Remote {
+                    accession,
+                    location: Box::new(location),
+                })
+            }
+            (_, Some(b'(')) if name_length > 0 => Err(self.error("unknown location operator")),
+            _ => self.parse_simple(),
+        }
+    }
+
+    fn parse_list(&mut self) -> Result<Vec<Location>, LocationError> {
+        let mut locations = vec![self.parse_location()?];
+        while self.eat(b",") {
+            locations.push(self.parse_location()?);
+        }
+        self.expect(b")", "expected ',' or ')'")?;
+        Ok(locations)
+    }
+
+    fn parse_position(&mut self) -> Result<Position, LocationError> {
+        match self.peek() {
+            Some(b'<') => {
+                self.offset += 1;
+                Ok(Position::Before(self.parse_number()?))
+            }
+            Some(b'>') => {
+                self.offset += 1;
+                Ok(Position::After(self.parse_number()?))
+            }
+            Some(b'(') => {
+                self.offset += 1;
+                let start = self.parse_number()?;
+                self.expect(b".", "expected '.' in within position")?;
+                let end = self.parse_number()?;
+                self.expect(b")", "expected ')' to close within position")?;
+                Ok(Position::Within(start, end))
+            }
+            Some(b) if b.is_ascii_digit() => Ok(Position::Exact(self.parse_number()?)),
+            Some(_) => Err(self.error("expected a position")),
+            None => Err(self.error("unexpected end of location")),
+        }
+    }
+
+    /// Single bases, ranges and between-base sites.
+    fn parse_simple(&mut self) -> Result<Location, LocationError> {
+        let start = self.parse_position()?;
+        if self.eat(b"..") {
+            let end = self.parse_position()?;
+            return Ok(Location::Range(start, end));
+        }
+
+        match (start, self.peek()) {
+            (Position::Exact(start), Some(b'^')) => {
+                self.offset += 1;
+                Ok(Location::Between(start, self.parse_number()?))
+            }
+            (Position::Exact(start), Some(b'.')) => {
+                self.offset += 1;
+                Ok(Location::Single(Position::Within(
+                    start,
+                    self.parse_number()?,
+                )))
+            }
+            (start, _) => Ok(Location::Single(start)),
+        }
+    }
+}
@@ -1,77 +1,740 @@
 use chrono::Local;
-use genbank_parser::{parse_sequence_record, Protein, Sequence};
-use rayon::prelude::*;
-use serde_json;
+use genbank_parser::{
+    parse_record, validate_record, write_bed, write_bed12, write_gff3, write_gff3_sequence,
+    write_gtf, CodingSequence, Fasta, FeatureType, GenbankReader, ParseError, Pipeline, Sequence,
+    ValidationReport, DEFAULT_PROTEIN_DEFLINE, DEFAULT_SEQUENCE_DEFLINE, GFF3_FASTA_DIRECTIVE,
+    GFF3_HEADER,
+};
+use serde::Serialize;
 use std::env;
-use std::fs;
-use std::path::Path;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+const USAGE: &str = "Usage: genbank-parser [validate] [--files-from <file>] \
+                     [--include <glob>]... [--exclude <glob>]... [--jobs <files>] \
+                     [--batch-size <records>] [--max-in-flight <batches>] \
+                     [--format json|fasta|gff3|gtf|bed|bed12] [--defline <template>] \
+                     [--protein-defline <template>] [--line-width <residues>] \
+                     [--gff-fasta] [--] <path>...";
+
+/// Where the `##FASTA` section of `features.gff3` is collected until the features are written.
+const GFF3_SEQUENCES: &str = "features.gff3.sequences";
+
+/// File names picked up from directories when no `--include` is given.
+const DEFAULT_INCLUDE: [&str; 12] = [
+    "*.seq",
+    "*.seq.gz",
+    "*.gb",
+    "*.gb.gz",
+    "*.gbk",
+    "*.gbk.gz",
+    "*.gbff",
+    "*.gbff.gz",
+    "*.embl",
+    "*.embl.gz",
+    "*.dat",
+    "*.dat.gz",
+];
+
+/// What the records are written as.
+enum Format {
+    /// `sequences.json`, `proteins.json` and `cds.json`
+    Json,
+    /// `sequences.fasta` and `proteins.fasta`
+    Fasta { sequences: Fasta, proteins: Fasta },
+    /// `features.gff3`, ending with the sequences if `fasta`
+    Gff3 { fasta: bool },
+    /// `features.gtf`
+    Gtf,
+    /// `features.bed`, as BED12 if `blocks` and otherwise BED6
+    Bed { blocks: bool },
+}
+
+struct Options {
+    validate: bool,
+    /// files and directories, including those listed by `--files-from`
+    paths: Vec<PathBuf>,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    /// number of files processed at the same time
+    jobs: usize,
+    pipeline: Pipeline,
+    format: Format,
+}
+
+fn parse_args(args: &[String]) -> Option<Options> {
+    let mut options = Options {
+        validate: false,
+        paths: Vec::new(),
+        include: Vec::new(),
+        exclude: Vec::new(),
+        jobs: 4,
+        pipeline: Pipeline::new(),
+        format: Format::Json,
+    };
+    let mut format = "json";
+    let mut defline = DEFAULT_SEQUENCE_DEFLINE;
+    let mut protein_defline = DEFAULT_PROTEIN_DEFLINE;
+    let mut line_width = 70;
+    let mut gff_fasta = false;
+
+    // `validate` is only the subcommand in first place, so it can't be confused with a path
+    let args = match args.split_first() {
+        Some((first, rest)) if first == "validate" => {
+            options.validate = true;
+            rest
+        }
+        _ => args,
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--files-from" => {
+                let list = args.next()?;
+                match read_file_list(Path::new(list)) {
+                    Ok(paths) => options.paths.extend(paths),
+                    Err(error) => {
+                        println!("Failed to read {}: {}", list, error);
+                        return None;
+                    }
+                }
+            }
+            "--include" => options.include.push(args.next()?.clone()),
+            "--exclude" => options.exclude.push(args.next()?.clone()),
+            "--jobs" => options.jobs = args.next()?.parse::<usize>().ok()?.max(1),
+            "--batch-size" => {
+                options.pipeline = options.pipeline.batch_size(args.next()?.parse().ok()?)
+            }
+            "--max-in-flight" => {
+                options.pipeline = options.pipeline.max_in_flight(args.next()?.parse().ok()?)
+            }
+            "--format" => format = args.next()?,
+            "--defline" => defline = args.next()?,
+            "--protein-defline" => protein_defline = args.next()?,
+            "--line-width" => line_width = args.next()?.parse().ok()?,
+            "--gff-fasta" => gff_fasta = true,
+            // everything after `--` is a path, even if it looks like an option
+            "--" => options.paths.extend(args.by_ref().map(PathBuf::from)),
+            _ if arg.starts_with("--") => return None,
+            _ => options.paths.push(PathBuf::from(arg)),
+        }
+    }
+
+    if options.paths.is_empty() {
+        return None;
+    }
+    options.format = match format {
+        "json" => Format::Json,
+        "fasta" => {
+            let fasta = |template| match Fasta::new(template) {
+                Ok(fasta) => Some(fasta.line_width(line_width)),
+                Err(error) => {
+                    println!("Invalid defline {}: {}", template, error);
+                    None
+                }
+            };
+            Format::Fasta {
+                sequences: fasta(defline)?,
+                proteins: fasta(protein_defline)?,
+            }
+        }
+        "gff3" => Format::Gff3 { fasta: gff_fasta },
+        "gtf" => Format::Gtf,
+        "bed" => Format::Bed { blocks: false },
+        "bed12" => Format::Bed { blocks: true },
+        _ => return None,
+    };
+    if options.include.is_empty() {
+        options.include = DEFAULT_INCLUDE
+            .iter()
+            .map(|glob| glob.to_string())
+            .collect();
+    }
+    Some(options)
+}
+
+/// Paths listed one per line; blank lines and lines starting with `#` are skipped.
+fn read_file_list(list: &Path) -> io::Result<Vec<PathBuf>> {
+    Ok(fs::read_to_string(list)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(PathBuf::from)
+        .collect())
+}
+
+/// Matches `*` (any run of characters) and `?` (any one character); everything else is literal.
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // where to resume after the last `*` if the rest fails to match
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == b'?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// Patterns with a `/` are matched against the path below the directory given on the command
+/// line, others against the file name.
+fn is_selected(path: &Path, root: &Path, options: &Options) -> bool {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let relative = path.strip_prefix(root).unwrap_or(path).to_string_lossy();
+    let matches = |glob: &String| {
+        let text = if glob.contains('/') { &relative } else { &name };
+        glob_match(glob.as_bytes(), text.as_bytes())
+    };
+    options.include.iter().any(matches) && !options.exclude.iter().any(matches)
+}
+
+fn collect_directory(
+    directory: &Path,
+    root: &Path,
+    options: &Options,
+    files: &mut Vec<PathBuf>,
+) -> io::Result<()> {
+    let mut entries = fs::read_dir(directory)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<PathBuf>>>()?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            collect_directory(&path, root, options, files)?;
+        } else if is_selected(&path, root, options) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Files named on the command line as they are, and the selected files below directories.
+fn collect_files(options: &Options) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in &options.paths {
+        if path.is_dir() {
+            collect_directory(path, path, options, &mut files)?;
+        } else {
+            files.push(path.clone());
+        }
+    }
+    Ok(files)
+}
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        println!("Usage: genbank-parser <file_path>");
+    let Some(options) = parse_args(&args[1..]) else {
+        println!("{}", USAGE);
+        std::process::exit(1);
+    };
+
+    if let Some(missing) = options.paths.iter().find(|path| !path.exists()) {
+        println!("File not found: {}", missing.display());
+        std::process::exit(1);
+    }
+    let files = match collect_files(&options) {
+        Ok(files) => files,
+        Err(error) => {
+            println!("Failed to list the input files: {}", error);
+            std::process::exit(1);
+        }
+    };
+    if files.is_empty() {
+        println!("No files selected");
         return;
     }
 
-    let file_path = &args[1];
-    if !Path::new(file_path).exists() {
-        println!("File not found: {}", file_path);
+    if options.validate {
+        println!(
+            "{} - validating records of {} files",
+            Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+            files.len()
+        );
+        let counts = match validate_genbank_files(&files, &options) {
+            Ok(counts) => counts,
+            Err(error) => {
+                println!("Failed to write the validation report: {}", error);
+                std::process::exit(1);
+            }
+        };
+        println!(
+            "{} - finished, {} records with issues, {} failed records and {} failed files",
+            Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+            counts.invalid,
+            counts.failed,
+            counts.failed_files
+        );
+        if counts.invalid > 0 || counts.failed > 0 || counts.failed_files > 0 {
+            std::process::exit(1);
+        }
         return;
     }
 
     println!(
-        "{} - reading sequences from file",
-        Local::now().format("%Y-%m-%d %H:%M:%S%.3f")
+        "{} - processing records of {} files",
+        Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+        files.len()
     );
-    let (sequences, proteins) = read_and_process_genbank_file(&file_path).unwrap();
+    let counts = match read_and_process_genbank_files(&files, &options) {
+        Ok(counts) => counts,
+        Err(error) => {
+            println!("Failed to write the output: {}", error);
+            std::process::exit(1);
+        }
+    };
     println!(
-        "{} - finished parsing (with {} sequences and {} proteins), writing to disk",
+        "{} - finished (with {} sequences, {} proteins, {} coding sequences, {} failed records \
+         and {} failed files)",
         Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
-        sequences.len(),
-        proteins.len()
+        counts.sequences,
+        counts.proteins,
+        counts.coding_sequences,
+        counts.failed,
+        counts.failed_files
     );
+    if counts.failed_files > 0 {
+        std::process::exit(1);
+    }
+}
+
+/// Runs `process_file` on up to `jobs` files at a time. A file that fails is reported and
+/// counted rather than stopping the others.
+fn for_each_file<F>(files: &[PathBuf], jobs: usize, process_file: F) -> usize
+where
+    F: Fn(&Path) -> io::Result<()> + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let failed = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        for _ in 0..jobs.min(files.len()) {
+            scope.spawn(|| {
+                while let Some(file) = files.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    if let Err(error) = process_file(file) {
+                        eprintln!("failed to process {}: {}", file.display(), error);
+                        failed.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            });
+        }
+    });
+    failed.into_inner()
+}
 
-    let sequences_json = serde_json::to_string(&sequences).unwrap();
-    let proteins_json = serde_json::to_string(&proteins).unwrap();
-    std::fs::write("sequences.json", sequences_json).expect("Failed to dump sequences.");
-    std::fs::write("proteins.json", proteins_json).expect("Failed to dump proteins.");
+/// Runs the pipeline over one file, checking the number of records against the loci listed by
+/// its release header if it has one.
+fn run_file<T, P, W>(file: &Path, pipeline: &Pipeline, process: P, mut write: W) -> io::Result<()>
+where
+    T: Send,
+    P: Fn(&[u8]) -> Result<T, ParseError> + Sync,
+    W: FnMut(Result<T, ParseError>) -> io::Result<()>,
+{
+    let mut reader = GenbankReader::open(file)?;
+    let loci = reader.release_header()?.map(|header| header.loci);
+    let mut records = 0;
+    pipeline.run(reader, process, |result| {
+        records += 1;
+        write(result)
+    })?;
 
-    println!(
-        "{} - finished",
-        Local::now().format("%Y-%m-%d %H:%M:%S%.3f")
-    );
+    if let Some(loci) = loci.filter(|&loci| loci != records) {
+        eprintln!(
+            "{}: the release header lists {} loci but {} records were read",
+            file.display(),
+            loci,
+            records
+        );
+    }
+    Ok(())
+}
+
+/// Writes records to a file one at a time, so they are never all held in memory: either as the
+/// elements of a JSON array or, for formats like FASTA, one after another.
+struct RecordWriter {
+    writer: BufWriter<File>,
+    json: bool,
+    empty: bool,
 }
 
-pub fn read_and_process_genbank_file(
-    file_path: &str,
-) -> std::io::Result<(Vec<Sequence>, Vec<Protein>)> {
-    let contents = fs::read(file_path)?;
+impl RecordWriter {
+    fn json_array(path: &str) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(b"[")?;
+        Ok(RecordWriter {
+            writer,
+            json: true,
+            empty: true,
+        })
+    }
+
+    fn concatenated(path: &str) -> io::Result<Self> {
+        Ok(RecordWriter {
+            writer: BufWriter::new(File::create(path)?),
+            json: false,
+            empty: true,
+        })
+    }
 
-    let mut start_index = 0;
-    let mut records = Vec::new();
-    for (i, window) in contents.windows(6).enumerate() {
-        if window.starts_with(b"\nLOCUS") {
-            records.push(&contents[start_index..i + 1]);
-            start_index = i + 1;
+    fn write_record(&mut self, record: &[u8]) -> io::Result<()> {
+        if self.json && !self.empty {
+            self.writer.write_all(b",")?;
         }
+        self.empty = false;
+        self.writer.write_all(record)
     }
 
-    if start_index < contents.len() {
-        records.push(&contents[start_index..]);
+    fn finish(mut self) -> io::Result<()> {
+        if self.json {
+            self.writer.write_all(b"]")?;
+        }
+        self.writer.flush()
     }
+}
 
-    println!(
-        "{} - processing records",
-        Local::now().format("%Y-%m-%d %H:%M:%S%.3f")
-    );
+/// An output record with the file it was read from.
+#[derive(Serialize)]
+struct Tagged<'a, T> {
+    source_file: &'a str,
+    #[serde(flatten)]
+    record: &'a T,
+}
+
+fn to_tagged_json<T: Serialize>(source_file: &str, record: &T) -> String {
+    // records are checked to be valid UTF-8 while parsing, so serializing can't fail
+    serde_json::to_string(&Tagged {
+        source_file,
+        record,
+    })
+    .unwrap()
+}
+
+/// A record parsed and serialized on a worker thread, ready to be appended to the outputs.
+struct SerializedRecord {
+    sequence: Vec<u8>,
+    proteins: Vec<Vec<u8>>,
+    coding_sequences: Vec<Vec<u8>>,
+    /// the entry for the `##FASTA` section of a GFF3 file
+    gff_sequence: Vec<u8>,
+}
+
+fn serialize_record(
+    record: &[u8],
+    source_file: &str,
+    format: &Format,
+) -> Result<SerializedRecord, ParseError> {
+    let (sequence, proteins) = parse_record(record)?;
+    let serialized = match format {
+        Format::Json => SerializedRecord {
+            sequence: to_tagged_json(source_file, &sequence).into_bytes(),
+            proteins: proteins
+                .iter()
+                .map(|protein| to_tagged_json(source_file, protein).into_bytes())
+                .collect(),
+            coding_sequences: extract_coding_sequences(&sequence)
+                .iter()
+                .map(|coding_sequence| to_tagged_json(source_file, coding_sequence).into_bytes())
+                .collect(),
+            gff_sequence: Vec::new(),
+        },
+        // writing to a Vec can't fail
+        Format::Fasta {
+            sequences,
+            proteins: protein_fasta,
+        } => {
+            let mut fasta = Vec::new();
+            sequences.write_sequence(&mut fasta, &sequence).unwrap();
+            SerializedRecord {
+                sequence: fasta,
+                proteins: proteins
+                    .iter()
+                    .map(|protein| {
+                        let mut fasta = Vec::new();
+                        protein_fasta
+                            .write_protein(&mut fasta, protein, &sequence)
+                            .unwrap();
+                        fasta
+                    })
+                    .collect(),
+                coding_sequences: Vec::new(),
+                gff_sequence: Vec::new(),
+            }
+        }
+        Format::Gff3 { fasta } => {
+            let mut features = Vec::new();
+            write_gff3(&mut features, &sequence).unwrap();
+            let mut gff_sequence = Vec::new();
+            if *fasta {
+                write_gff3_sequence(&mut gff_sequence, &sequence).unwrap();
+            }
+            SerializedRecord {
+                sequence: features,
+                proteins: Vec::new(),
+                coding_sequences: Vec::new(),
+                gff_sequence,
+            }
+        }
+        Format::Gtf | Format::Bed { .. } => {
+            let mut features = Vec::new();
+            match format {
+                Format::Bed { blocks: true } => write_bed12(&mut features, &sequence),
+                Format::Bed { blocks: false } => write_bed(&mut features, &sequence),
+                _ => write_gtf(&mut features, &sequence),
+            }
+            .unwrap();
+            SerializedRecord {
+                sequence: features,
+                proteins: Vec::new(),
+                coding_sequences: Vec::new(),
+                gff_sequence: Vec::new(),
+            }
+        }
+    };
+    Ok(serialized)
+}
+
+#[derive(Default)]
+struct Counts {
+    sequences: usize,
+    proteins: usize,
+    coding_sequences: usize,
+    failed: usize,
+    failed_files: usize,
+}
+
+/// The files written, those a format has no use for left out.
+struct Outputs {
+    /// the records themselves, or their features
+    sequences: RecordWriter,
+    proteins: Option<RecordWriter>,
+    coding_sequences: Option<RecordWriter>,
+    gff_sequences: Option<RecordWriter>,
+    counts: Counts,
+}
+
+impl Outputs {
+    fn create(format: &Format) -> io::Result<Self> {
+        let (sequences, proteins, coding_sequences, gff_sequences) = match format {
+            Format::Json => (
+                RecordWriter::json_array("sequences.json")?,
+                Some(RecordWriter::json_array("proteins.json")?),
+                Some(RecordWriter::json_array("cds.json")?),
+                None,
+            ),
+            Format::Fasta { .. } => (
+                RecordWriter::concatenated("sequences.fasta")?,
+                Some(RecordWriter::concatenated("proteins.fasta")?),
+                None,
+                None,
+            ),
+            Format::Gff3 { fasta } => {
+                let mut features = RecordWriter::concatenated("features.gff3")?;
+                features.write_record(GFF3_HEADER.as_bytes())?;
+                let gff_sequences = if *fasta {
+                    Some(RecordWriter::concatenated(GFF3_SEQUENCES)?)
+                } else {
+                    None
+                };
+                (features, None, None, gff_sequences)
+            }
+            Format::Gtf => (
+                RecordWriter::concatenated("features.gtf")?,
+                None,
+                None,
+                None,
+            ),
+            Format::Bed { .. } => (
+                RecordWriter::concatenated("features.bed")?,
+                None,
+                None,
+                None,
+            ),
+        };
+        Ok(Outputs {
+            sequences,
+            proteins,
+            coding_sequences,
+            gff_sequences,
+            counts: Counts::default(),
+        })
+    }
+
+    fn write(&mut self, record: SerializedRecord) -> io::Result<()> {
+        self.sequences.write_record(&record.sequence)?;
+        self.counts.sequences += 1;
+        if let Some(proteins) = &mut self.proteins {
+            for protein in &record.proteins {
+                proteins.write_record(protein)?;
+            }
+            self.counts.proteins += record.proteins.len();
+        }
+        if let Some(coding_sequences) = &mut self.coding_sequences {
+            for coding_sequence in &record.coding_sequences {
+                coding_sequences.write_record(coding_sequence)?;
+            }
+            self.counts.coding_sequences += record.coding_sequences.len();
+        }
+        if let Some(gff_sequences) = &mut self.gff_sequences {
+            gff_sequences.write_record(&record.gff_sequence)?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<Counts> {
+        // the sequences go after every feature, so they are only added once all are written
+        if let Some(gff_sequences) = self.gff_sequences {
+            gff_sequences.finish()?;
+            if self.counts.sequences > 0 {
+                self.sequences
+                    .write_record(GFF3_FASTA_DIRECTIVE.as_bytes())?;
+                io::copy(&mut File::open(GFF3_SEQUENCES)?, &mut self.sequences.writer)?;
+            }
+            fs::remove_file(GFF3_SEQUENCES)?;
+        }
+        self.sequences.finish()?;
+        for output in [self.proteins, self.coding_sequences].into_iter().flatten() {
+            output.finish()?;
+        }
+        Ok(self.counts)
+    }
+}
+
+/// Streams the records of every file into the outputs of the chosen format. Records keep their
+/// order within a file, but records of files processed at the same time are interleaved.
+fn read_and_process_genbank_files(files: &[PathBuf], options: &Options) -> io::Result<Counts> {
+    let outputs = Mutex::new(Outputs::create(&options.format)?);
+
+    let failed_files = for_each_file(files, options.jobs, |file| {
+        let source_file = file.to_string_lossy();
+        let process = |record: &[u8]| serialize_record(record, &source_file, &options.format);
+        run_file(file, &options.pipeline, process, |result| {
+            let mut outputs = outputs.lock().unwrap();
+            // a bad record is reported and skipped rather than failing the whole file
+            let record = match result {
+                Ok(record) => record,
+                Err(error) => {
+                    eprintln!("skipping record of {}: {}", source_file, error);
+                    outputs.counts.failed += 1;
+                    return Ok(());
+                }
+            };
+
+            outputs.write(record)
+        })
+    });
+
+    let counts = outputs.into_inner().unwrap().finish()?;
+    Ok(Counts {
+        failed_files,
+        ..counts
+    })
+}
+
+/// Nucleotide sequences of every CDS feature, skipping (and reporting) ones that can't be
+/// extracted, e.g. because they refer to another record.
+fn extract_coding_sequences(sequence: &Sequence) -> Vec<CodingSequence> {
+    sequence
+        .features
+        .iter()
+        .filter(|feature| feature.feature_type == Some(FeatureType::Cds))
+        .filter_map(|feature| match sequence.feature_sequence(feature) {
+            Ok(nucleotides) => Some(CodingSequence {
+                protein_id: feature
+                    .qualifier(b"protein_id")
+                    .unwrap_or_default()
+                    .to_vec(),
+                source_id: sequence.version.clone(),
+                sequence: nucleotides,
+                location: feature.location.clone(),
+            }),
+            Err(error) => {
+                eprintln!(
+                    "skipping CDS {} of {}: {}",
+                    String::from_utf8_lossy(&feature.location),
+                    String::from_utf8_lossy(&sequence.version),
+                    error
+                );
+                None
+            }
+        })
+        .collect()
+}
+
+fn print_report(source_file: &str, report: &ValidationReport) {
+    let source_id = String::from_utf8_lossy(&report.source_id);
+    for issue in &report.issues {
+        println!("{} {}: {}", source_file, source_id, issue);
+    }
+    for cds in &report.cds {
+        for issue in &cds.issues {
+            println!(
+                "{} {} {} {}: {}",
+                source_file,
+                source_id,
+                String::from_utf8_lossy(&cds.protein_id),
+                String::from_utf8_lossy(&cds.location),
+                issue
+            );
+        }
+    }
+}
+
+#[derive(Default)]
+struct ValidationCounts {
+    invalid: usize,
+    failed: usize,
+    failed_files: usize,
+}
 
-    let (sequences, proteins): (Vec<Sequence>, Vec<Vec<Protein>>) = records
-        .par_iter()
-        .skip(1) // first element of records is file header
-        .map(|record| parse_sequence_record(record))
-        .unzip();
+/// Validates every record, printing one line per problem and writing the reports of records
+/// with issues to `validation.json`.
+fn validate_genbank_files(files: &[PathBuf], options: &Options) -> io::Result<ValidationCounts> {
+    let reports = Mutex::new((
+        RecordWriter::json_array("validation.json")?,
+        ValidationCounts::default(),
+    ));
 
-    let proteins: Vec<Protein> = proteins.into_iter().flatten().collect();
+    let failed_files = for_each_file(files, options.jobs, |file| {
+        let source_file = file.to_string_lossy();
+        run_file(file, &options.pipeline, validate_record, |result| {
+            let (reports, counts) = &mut *reports.lock().unwrap();
+            match result {
+                Ok(report) if report.is_valid() => Ok(()),
+                Ok(report) => {
+                    print_report(&source_file, &report);
+                    counts.invalid += 1;
+                    reports.write_record(to_tagged_json(&source_file, &report).as_bytes())
+                }
+                Err(error) => {
+                    eprintln!("skipping record of {}: {}", source_file, error);
+                    counts.failed += 1;
+                    Ok(())
+                }
+            }
+        })
+    });
 
-    Ok((sequences, proteins))
+    let (reports, counts) = reports.into_inner().unwrap();
+    reports.finish()?;
+    Ok(ValidationCounts {
+        failed_files,
+        ..counts
+    })
 }
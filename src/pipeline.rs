@@ -0,0 +1,168 @@
+use crate::{GenbankReader, ParseError};
+use rayon::prelude::*;
+use std::collections::BTreeMap;
+use std::io::{self, BufRead};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::thread;
+
+/// Processes the records of a flat file in parallel while holding only a bounded number of
+/// them in memory.
+///
+/// A reader thread splits the input into batches of records, batches are processed on the rayon
+/// thread pool, and the results are handed back in file order. At most `max_in_flight` batches
+/// are read but not yet written at any time, so a slow batch holds the reader back rather than
+/// letting finished ones pile up behind it.
+#[derive(Debug, Clone)]
+pub struct Pipeline {
+    batch_size: usize,
+    max_in_flight: usize,
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Pipeline {
+            batch_size: 64,
+            max_in_flight: 2 * rayon::current_num_threads(),
+        }
+    }
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Pipeline::default()
+    }
+
+    /// Number of records handed to the worker pool at a time, at least 1.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Number of batches that may be between the reader and the writer, at least 1.
+    pub fn max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.max_in_flight = max_in_flight.max(1);
+        self
+    }
+
+    /// Runs `process` on the raw bytes of every record of `reader` in parallel and `write` on
+    /// the results in record order.
+    ///
+    /// Records the reader reports as truncated reach `write` as errors in their place. Stops at
+    /// the first error from reading the input or from `write`.
+    pub fn run<R, T, P, W>(&self, reader: GenbankReader<R>, process: P, write: W) -> io::Result<()>
+    where
+        R: BufRead + Send,
+        T: Send,
+        P: Fn(&[u8]) -> Result<T, ParseError> + Sync,
+        W: FnMut(Result<T, ParseError>) -> io::Result<()>,
+    {
+        // a permit is taken for each batch read and given back once it has been written
+        let (permit_sender, permits) = sync_channel(self.max_in_flight);
+        for _ in 0..self.max_in_flight {
+            permit_sender.send(()).unwrap();
+        }
+        let (batch_sender, batches) = sync_channel::<(usize, Batch)>(self.max_in_flight);
+        let (result_sender, results) =
+            sync_channel::<(usize, Vec<Result<T, ParseError>>)>(self.max_in_flight);
+
+        thread::scope(|scope| {
+            let batch_size = self.batch_size;
+            let read = scope.spawn(move || read_batches(reader, batch_size, permits, batch_sender));
+
+            let process = &process;
+            scope.spawn(move || {
+                // in place, so waiting for batches doesn't tie up a thread of the pool
+                rayon::in_place_scope(|pool| {
+                    for (index, batch) in batches {
+                        let result_sender = result_sender.clone();
+                        pool.spawn(move |_| {
+                            let processed = batch
+                                .into_par_iter()
+                                .map(|record| record.and_then(|record| process(&record)))
+                                .collect();
+                            // the writer has stopped if this fails, so there is nobody to tell
+                            _ = result_sender.send((index, processed));
+                        });
+                    }
+                });
+            });
+
+            let written = write_in_order(results, permit_sender, write);
+            // a failed write has dropped the permits, so the reader is stopped by now
+            let read = read.join().expect("reader thread panicked");
+            written.and(read)
+        })
+    }
+}
+
+/// Raw records, or why one couldn't be read, in file order.
+type Batch = Vec<Result<Vec<u8>, ParseError>>;
+
+fn read_batches<R: BufRead>(
+    mut reader: GenbankReader<R>,
+    batch_size: usize,
+    permits: Receiver<()>,
+    batches: SyncSender<(usize, Batch)>,
+) -> io::Result<()> {
+    for index in 0.. {
+        if permits.recv().is_err() {
+            // the writer has stopped
+            return Ok(());
+        }
+
+        let mut batch = Vec::with_capacity(batch_size);
+        let mut last = false;
+        let mut failed = None;
+        while batch.len() < batch_size {
+            match reader.next_record() {
+                Ok(Some(record)) => batch.push(Ok(record)),
+                Ok(None) => {
+                    last = true;
+                    break;
+                }
+                Err(ParseError::Io { kind, message, .. }) => {
+                    // the records read so far are still written before the error
+                    failed = Some(io::Error::new(kind, message));
+                    break;
+                }
+                Err(error) => batch.push(Err(error)),
+            }
+        }
+
+        if !batch.is_empty() && batches.send((index, batch)).is_err() {
+            return Ok(());
+        }
+        if let Some(error) = failed {
+            return Err(error);
+        }
+        if last {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn write_in_order<T, W>(
+    results: Receiver<(usize, Vec<Result<T, ParseError>>)>,
+    permits: SyncSender<()>,
+    mut write: W,
+) -> io::Result<()>
+where
+    W: FnMut(Result<T, ParseError>) -> io::Result<()>,
+{
+    // batches finished ahead of an earlier one wait here
+    let mut pending = BTreeMap::new();
+    let mut next = 0;
+    for (index, processed) in results {
+        pending.insert(index, processed);
+        while let Some(processed) = pending.remove(&next) {
+            for result in processed {
+                write(result)?;
+            }
+            next += 1;
+            // the reader may already be done, so nobody needs the permit
+            _ = permits.send(());
+        }
+    }
+    Ok(())
+}
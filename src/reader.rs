@@ -0,0 +1,200 @@
+use crate::embl::is_id_line;
+use crate::header::{parse_release_header, ReleaseHeader};
+use crate::{parse_record, record_accession, ParseError, Protein, Sequence};
+use flate2::bufread::MultiGzDecoder;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+/// A parsed record: the sequence and the proteins of its CDS features.
+pub type Record = (Sequence, Vec<Protein>);
+
+/// An input that may have been decompressed on the way in.
+pub type Input = Box<dyn BufRead + Send>;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Text before the first record kept for the release header; a real one is under 1 KiB.
+const MAX_HEADER_LENGTH: usize = 64 * 1024;
+
+/// `reader`, decompressed on the fly if it starts with the gzip magic bytes whatever its file
+/// name. Concatenated gzip members, e.g. from `cat a.gz b.gz`, are read one after another.
+pub fn decompress<R: BufRead + Send + 'static>(mut reader: R) -> io::Result<Input> {
+    if reader.fill_buf()?.starts_with(&GZIP_MAGIC) {
+        Ok(Box::new(BufReader::new(MultiGzDecoder::new(reader))))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+/// `LOCUS` at the start of a line, followed by whitespace.
+#[inline]
+fn is_locus_line(line: &[u8]) -> bool {
+    line.strip_prefix(b"LOCUS")
+        .is_some_and(|rest| rest.first().is_some_and(u8::is_ascii_whitespace))
+}
+
+/// The first line of a GenBank or EMBL record.
+#[inline]
+fn is_record_start(line: &[u8]) -> bool {
+    is_locus_line(line) || is_id_line(line)
+}
+
+/// Appends `line` to `record`, turning a CRLF line ending into LF.
+#[inline]
+fn append_line(record: &mut Vec<u8>, line: &[u8]) {
+    match line.strip_suffix(b"\r\n") {
+        Some(line) => {
+            record.extend_from_slice(line);
+            record.push(b'\n');
+        }
+        None => record.extend_from_slice(line.strip_suffix(b"\r").unwrap_or(line)),
+    }
+}
+
+/// Reads a GenBank or EMBL flat file one record at a time, so only the record being read is
+/// held in memory however large the file is.
+///
+/// Records start at a `LOCUS` line, or an `ID` line in EMBL files, and end at a `//` line.
+/// Anything before the first one is parsed as the release file header if it is one, and
+/// otherwise skipped, so files without a header (e.g. from efetch) lose no records.
+pub struct GenbankReader<R> {
+    reader: R,
+    line: Vec<u8>,
+    /// the record being read, from its LOCUS or ID line
+    record: Vec<u8>,
+    /// set once everything up to the first record has been read
+    header: Option<Option<ReleaseHeader>>,
+    finished: bool,
+}
+
+impl GenbankReader<Input> {
+    /// Opens a flat file, plain or gzip compressed.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = BufReader::new(File::open(path)?);
+        Ok(GenbankReader::new(decompress(file)?))
+    }
+}
+
+impl<R: BufRead> GenbankReader<R> {
+    pub fn new(reader: R) -> Self {
+        GenbankReader {
+            reader,
+            line: Vec::new(),
+            record: Vec::new(),
+            header: None,
+            finished: false,
+        }
+    }
+
+    /// The release file header, reading up to the first record if that hasn't happened yet.
+    pub fn release_header(&mut self) -> io::Result<Option<&ReleaseHeader>> {
+        if self.header.is_none() {
+            let mut header = Vec::new();
+            loop {
+                self.line.clear();
+                if self.reader.read_until(b'\n', &mut self.line)? == 0 {
+                    break;
+                }
+                if is_record_start(&self.line) {
+                    append_line(&mut self.record, &self.line);
+                    break;
+                }
+                if header.len() < MAX_HEADER_LENGTH {
+                    header.extend_from_slice(&self.line);
+                }
+            }
+            self.header = Some(parse_release_header(&header));
+        }
+        Ok(self.header.as_ref().and_then(Option::as_ref))
+    }
+
+    fn io_error(&self, error: io::Error) -> ParseError {
+        ParseError::Io {
+            accession: record_accession(&self.record),
+            kind: error.kind(),
+            message: error.to_string(),
+        }
+    }
+
+    /// Reads the next line into `self.line`, returning `false` at the end of the input.
+    fn read_line(&mut self) -> Result<bool, ParseError> {
+        self.line.clear();
+        match self.reader.read_until(b'\n', &mut self.line) {
+            Ok(read) => Ok(read > 0),
+            Err(error) => Err(self.io_error(error)),
+        }
+    }
+
+    /// Raw bytes of the next record, from its LOCUS or ID line up to but not including its `//`
+    /// line, with line endings normalised to `\n`. `None` at the end of the input.
+    ///
+    /// A record cut short by the end of the input or by the start of another is reported as
+    /// `TruncatedRecord` rather than returned, and reading can carry on after it; after an
+    /// `Io` error it can't.
+    pub fn next_record(&mut self) -> Result<Option<Vec<u8>>, ParseError> {
+        if let Err(error) = self.release_header().map(|_| ()) {
+            return Err(self.io_error(error));
+        }
+
+        loop {
+            if !self.read_line()? {
+                if self.record.is_empty() {
+                    return Ok(None);
+                }
+                let record = std::mem::take(&mut self.record);
+                return Err(ParseError::TruncatedRecord {
+                    accession: record_accession(&record),
+                });
+            }
+
+            if self.record.is_empty() {
+                // anything between a `//` and the start of the next record is skipped
+                if is_record_start(&self.line) {
+                    append_line(&mut self.record, &self.line);
+                }
+                continue;
+            }
+            if self.line.trim_ascii_end() == b"//" {
+                return Ok(Some(std::mem::take(&mut self.record)));
+            }
+            if is_record_start(&self.line) {
+                // the line read here starts the next record
+                let record = std::mem::take(&mut self.record);
+                append_line(&mut self.record, &self.line);
+                return Err(ParseError::TruncatedRecord {
+                    accession: record_accession(&record),
+                });
+            }
+            append_line(&mut self.record, &self.line);
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: BufRead> Iterator for GenbankReader<R> {
+    type Item = Result<Record, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        match self.next_record() {
+            Ok(Some(record)) => Some(parse_record(&record)),
+            Ok(None) => {
+                self.finished = true;
+                None
+            }
+            // the input can't be trusted after a failed read, so iteration stops here
+            Err(error @ ParseError::Io { .. }) => {
+                self.finished = true;
+                Some(Err(error))
+            }
+            Err(error) => Some(Err(error)),
+        }
+    }
+}
@@ -0,0 +1,427 @@
+use crate::extract::ExtractError;
+use crate::location::{Location, Strand};
+use crate::{Feature, Sequence};
+use std::fmt;
+
+/// An NCBI genetic code, see https://www.ncbi.nlm.nih.gov/Taxonomy/Utils/wprintgc.cgi
+///
+/// `amino_acids` and `starts` are indexed by codon in TCAG order (TTT, TTC, TTA, TTG, TCT, ...),
+/// as in the NCBI `gc.prt` file.
+#[derive(Debug)]
+pub struct GeneticCode {
+    pub id: u8,
+    pub name: &'static str,
+    amino_acids: &'static [u8; 64],
+    starts: &'static [u8; 64],
+}
+
+const fn code(
+    id: u8,
+    name: &'static str,
+    amino_acids: &'static [u8; 64],
+    starts: &'static [u8; 64],
+) -> GeneticCode {
+    GeneticCode {
+        id,
+        name,
+        amino_acids,
+        starts,
+    }
+}
+
+#[rustfmt::skip]
+pub static GENETIC_CODES: [GeneticCode; 26] = [
+    code(1, "Standard",
+        b"FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        b"---M------**--*----M---------------M----------------------------"),
+    code(2, "Vertebrate Mitochondrial",
+        b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSS**VVVVAAAADDEEGGGG",
+        b"----------**--------------------MMMM----------**---M------------"),
+    code(3, "Yeast Mitochondrial",
+        b"FFLLSSSSYY**CCWWTTTTPPPPHHQQRRRRIIMMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        b"----------**----------------------MM---------------M------------"),
+    code(4, "Mold, Protozoan, and Coelenterate Mitochondrial and Mycoplasma/Spiroplasma",
+        b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        b"--MM------**-------M------------MMMM---------------M------------"),
+    code(5, "Invertebrate Mitochondrial",
+        b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSSSSVVVVAAAADDEEGGGG",
+        b"---M------**--------------------MMMM---------------M------------"),
+    code(6, "Ciliate, Dasycladacean and Hexamita Nuclear",
+        b"FFLLSSSSYYQQCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        b"--------------*--------------------M----------------------------"),
+    code(9, "Echinoderm and Flatworm Mitochondrial",
+        b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNNKSSSSVVVVAAAADDEEGGGG",
+        b"----------**-----------------------M---------------M------------"),
+    code(10, "Euplotid Nuclear",
+        b"FFLLSSSSYY**CCCWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        b"----------**-----------------------M----------------------------"),
+    code(11, "Bacterial, Archaeal and Plant Plastid",
+        b"FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        b"---M------**--*----M------------MMMM---------------M------------"),
+    code(12, "Alternative Yeast Nuclear",
+        b"FFLLSSSSYY**CC*WLLLSPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        b"----------**--*----M---------------M----------------------------"),
+    code(13, "Ascidian Mitochondrial",
+        b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSSGGVVVVAAAADDEEGGGG",
+        b"---M------**----------------------MM---------------M------------"),
+    code(14, "Alternative Flatworm Mitochondrial",
+        b"FFLLSSSSYYY*CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNNKSSSSVVVVAAAADDEEGGGG",
+        b"-----------*-----------------------M----------------------------"),
+    code(16, "Chlorophycean Mitochondrial",
+        b"FFLLSSSSYY*LCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        b"----------*---*--------------------M----------------------------"),
+    code(21, "Trematode Mitochondrial",
+        b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNNKSSSSVVVVAAAADDEEGGGG",
+        b"----------**-----------------------M---------------M------------"),
+    code(22, "Scenedesmus obliquus Mitochondrial",
+        b"FFLLSS*SYY*LCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        b"------*---*---*--------------------M----------------------------"),
+    code(23, "Thraustochytrium Mitochondrial",
+        b"FF*LSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        b"--*-------**--*-----------------M--M---------------M------------"),
+    code(24, "Rhabdopleuridae Mitochondrial",
+        b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSSKVVVVAAAADDEEGGGG",
+        b"---M------**-------M---------------M---------------M------------"),
+    code(25, "Candidate Division SR1 and Gracilibacteria",
+        b"FFLLSSSSYY**CCGWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        b"---M------**-----------------------M---------------M------------"),
+    code(26, "Pachysolen tannophilus Nuclear",
+        b"FFLLSSSSYY**CC*WLLLAPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        b"----------**--*----M---------------M----------------------------"),
+    code(27, "Karyorelict Nuclear",
+        b"FFLLSSSSYYQQCCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        b"--------------*--------------------M----------------------------"),
+    code(28, "Condylostoma Nuclear",
+        b"FFLLSSSSYYQQCCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        b"----------**--*--------------------M----------------------------"),
+    code(29, "Mesodinium Nuclear",
+        b"FFLLSSSSYYYYCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        b"--------------*--------------------M----------------------------"),
+    code(30, "Peritrich Nuclear",
+        b"FFLLSSSSYYEECC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        b"--------------*--------------------M----------------------------"),
+    code(31, "Blastocrithidia Nuclear",
+        b"FFLLSSSSYYEECCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        b"----------**-----------------------M----------------------------"),
+    code(32, "Balanophoraceae Plastid",
+        b"FFLLSSSSYY*WCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        b"---M------*---*----M------------MMMM---------------M------------"),
+    code(33, "Cephalodiscidae Mitochondrial",
+        b"FFLLSSSSYYY*CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSSKVVVVAAAADDEEGGGG",
+        b"---M-------*-------M---------------M---------------M------------"),
+];
+
+/// Unambiguous bases an IUPAC nucleotide code stands for, as TCAG indices.
+#[inline]
+fn base_indices(base: u8) -> &'static [usize] {
+    match base.to_ascii_uppercase() {
+        b'T' | b'U' => &[0],
+        b'C' => &[1],
+        b'A' => &[2],
+        b'G' => &[3],
+        b'Y' => &[0, 1],
+        b'R' => &[2, 3],
+        b'W' => &[0, 2],
+        b'S' => &[1, 3],
+        b'K' => &[0, 3],
+        b'M' => &[1, 2],
+        b'B' => &[0, 1, 3],
+        b'D' => &[0, 2, 3],
+        b'H' => &[0, 1, 2],
+        b'V' => &[1, 2, 3],
+        b'N' => &[0, 1, 2, 3],
+        _ => &[],
+    }
+}
+
+/// Index of an unambiguous codon.
+#[inline]
+fn codon_index(codon: &[u8]) -> Option<usize> {
+    let index = |base: u8| match base.to_ascii_uppercase() {
+        b'T' | b'U' => Some(0),
+        b'C' => Some(1),
+        b'A' => Some(2),
+        b'G' => Some(3),
+        _ => None,
+    };
+    match codon {
+        [first, second, third] => Some(index(*first)? * 16 + index(*second)? * 4 + index(*third)?),
+        _ => None,
+    }
+}
+
+/// Every unambiguous codon a (possibly ambiguous) codon may stand for.
+fn codon_indices(codon: &[u8]) -> Vec<usize> {
+    let mut indices = vec![0];
+    for &base in codon {
+        indices = indices
+            .iter()
+            .flat_map(|index| base_indices(base).iter().map(move |b| index * 4 + b))
+            .collect();
+    }
+    indices
+}
+
+impl GeneticCode {
+    pub fn from_id(id: u8) -> Option<&'static GeneticCode> {
+        GENETIC_CODES.iter().find(|code| code.id == id)
+    }
+
+    pub fn standard() -> &'static GeneticCode {
+        &GENETIC_CODES[0]
+    }
+
+    /// Amino acid for a codon, resolving IUPAC ambiguity codes where every possible codon
+    /// agrees (or to `B`, `Z` or `J`); otherwise `X`.
+    pub fn translate_codon(&self, codon: &[u8]) -> u8 {
+        if let Some(index) = codon_index(codon) {
+            return self.amino_acids[index];
+        }
+
+        let mut amino_acids: Vec<u8> = codon_indices(codon)
+            .into_iter()
+            .map(|index| self.amino_acids[index])
+            .collect();
+        amino_acids.sort_unstable();
+        amino_acids.dedup();
+        match amino_acids.as_slice() {
+            [amino_acid] => *amino_acid,
+            b"DN" => b'B',
+            b"EQ" => b'Z',
+            b"IL" => b'J',
+            _ => b'X',
+        }
+    }
+
+    /// Whether every codon `codon` may stand for is an initiation codon.
+    pub fn is_start(&self, codon: &[u8]) -> bool {
+        let indices = codon_indices(codon);
+        !indices.is_empty() && indices.iter().all(|&index| self.starts[index] == b'M')
+    }
+
+    pub fn is_stop(&self, codon: &[u8]) -> bool {
+        codon.len() == 3 && self.translate_codon(codon) == b'*'
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TranslationError {
+    Extract(ExtractError),
+    UnknownGeneticCode(Vec<u8>),
+    InvalidCodonStart(Vec<u8>),
+    InvalidTranslExcept(Vec<u8>),
+}
+
+impl fmt::Display for TranslationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TranslationError::Extract(error) => write!(f, "{}", error),
+            TranslationError::UnknownGeneticCode(table) => {
+                write!(
+                    f,
+                    "unknown genetic code: {}",
+                    String::from_utf8_lossy(table)
+                )
+            }
+            TranslationError::InvalidCodonStart(codon_start) => write!(
+                f,
+                "invalid codon_start: {}",
+                String::from_utf8_lossy(codon_start)
+            ),
+            TranslationError::InvalidTranslExcept(transl_except) => write!(
+                f,
+                "invalid transl_except: {}",
+                String::from_utf8_lossy(transl_except)
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TranslationError {}
+
+impl From<ExtractError> for TranslationError {
+    fn from(error: ExtractError) -> Self {
+        TranslationError::Extract(error)
+    }
+}
+
+/// How to translate a coding sequence, usually taken from the qualifiers of a CDS feature.
+#[derive(Debug)]
+pub struct TranslationOptions {
+    pub genetic_code: &'static GeneticCode,
+    /// 1, 2 or 3, the `/codon_start` of the first complete codon
+    pub codon_start: usize,
+    /// `<` at the 5' end; the first codon is not translated as an initiator
+    pub five_prime_partial: bool,
+    /// `>` at the 3' end; a trailing incomplete codon is translated if it is unambiguous
+    pub three_prime_partial: bool,
+    /// (codon index, amino acid) overrides from `/transl_except`
+    pub exceptions: Vec<(usize, u8)>,
+}
+
+impl Default for TranslationOptions {
+    fn default() -> Self {
+        TranslationOptions {
+            genetic_code: GeneticCode::standard(),
+            codon_start: 1,
+            five_prime_partial: false,
+            three_prime_partial: false,
+            exceptions: Vec::new(),
+        }
+    }
+}
+
+/// Translates a coding sequence the way the `/translation` qualifier is written: an initiation
+/// codon at the start becomes `M`, and a terminal stop codon is left off.
+pub fn translate(nucleotides: &[u8], options: &TranslationOptions) -> Vec<u8> {
+    let code = options.genetic_code;
+    let coding = nucleotides
+        .get(options.codon_start.saturating_sub(1)..)
+        .unwrap_or_default();
+
+    let mut protein: Vec<u8> = coding
+        .chunks(3)
+        .enumerate()
+        .filter_map(|(i, codon)| {
+            if codon.len() < 3 {
+                // pad the incomplete codon and keep it if the padding doesn't matter
+                if !options.three_prime_partial {
+                    return None;
+                }
+                let mut padded = codon.to_vec();
+                padded.resize(3, b'n');
+                let amino_acid = code.translate_codon(&padded);
+                return (amino_acid != b'X').then_some(amino_acid);
+            }
+            if i == 0 && !options.five_prime_partial && code.is_start(codon) {
+                return Some(b'M');
+            }
+            Some(code.translate_codon(codon))
+        })
+        .collect();
+
+    for &(index, amino_acid) in &options.exceptions {
+        if index < protein.len() {
+            protein[index] = amino_acid;
+        } else if index == protein.len() {
+            // an exception can complete a trailing partial codon, e.g. a stop from polyadenylation
+            protein.push(amino_acid);
+        }
+    }
+
+    if protein.last() == Some(&b'*') {
+        protein.pop();
+    }
+    protein
+}
+
+/// One letter code for the three letter amino acid names used by `/transl_except`.
+fn amino_acid_code(name: &[u8]) -> Option<u8> {
+    let code = match name {
+        b"Ala" => b'A',
+        b"Arg" => b'R',
+        b"Asn" => b'N',
+        b"Asp" => b'D',
+        b"Cys" => b'C',
+        b"Gln" => b'Q',
+        b"Glu" => b'E',
+        b"Gly" => b'G',
+        b"His" => b'H',
+        b"Ile" => b'I',
+        b"Leu" => b'L',
+        b"Lys" => b'K',
+        b"Met" => b'M',
+        b"Phe" => b'F',
+        b"Pro" => b'P',
+        b"Ser" => b'S',
+        b"Thr" => b'T',
+        b"Trp" => b'W',
+        b"Tyr" => b'Y',
+        b"Val" => b'V',
+        b"Sec" => b'U',
+        b"Pyl" => b'O',
+        b"Asx" => b'B',
+        b"Glx" => b'Z',
+        b"Xle" => b'J',
+        b"TERM" => b'*',
+        b"OTHER" => b'X',
+        _ => return None,
+    };
+    Some(code)
+}
+
+/// Parses `(pos:complement(4918..4920),aa:Sec)` into the location and amino acid.
+fn parse_transl_except(value: &[u8]) -> Option<(Location, u8)> {
+    let value = value.strip_prefix(b"(")?.strip_suffix(b")")?;
+    let value = value.strip_prefix(b"pos:")?;
+    let separator = value.windows(4).rposition(|window| window == b",aa:")?;
+    let location = Location::parse(&value[..separator]).ok()?;
+    let amino_acid = amino_acid_code(&value[separator + 4..])?;
+    Some((location, amino_acid))
+}
+
+impl Sequence {
+    /// Translation options for a CDS from its `/transl_table`, `/codon_start` and
+    /// `/transl_except` qualifiers and the partial markers of its location.
+    pub fn translation_options(
+        &self,
+        feature: &Feature,
+    ) -> Result<TranslationOptions, TranslationError> {
+        let location = feature.parsed_location().map_err(ExtractError::from)?;
+
+        let genetic_code = match feature.qualifier(b"transl_table") {
+            Some(table) => std::str::from_utf8(table)
+                .ok()
+                .and_then(|table| table.parse().ok())
+                .and_then(GeneticCode::from_id)
+                .ok_or_else(|| TranslationError::UnknownGeneticCode(table.to_vec()))?,
+            None => GeneticCode::standard(),
+        };
+
+        let codon_start = match feature.qualifier(b"codon_start") {
+            Some(codon_start) => match codon_start {
+                b"1" => 1,
+                b"2" => 2,
+                b"3" => 3,
+                _ => return Err(TranslationError::InvalidCodonStart(codon_start.to_vec())),
+            },
+            None => 1,
+        };
+
+        let mut exceptions = Vec::new();
+        for value in feature.qualifiers_named(b"transl_except") {
+            let invalid = || TranslationError::InvalidTranslExcept(value.to_vec());
+            let (position, amino_acid) = parse_transl_except(value).ok_or_else(invalid)?;
+            // the first base of the codon, in reading order
+            let first_base = position
+                .spans()
+                .first()
+                .map(|span| match span.strand {
+                    Strand::Forward => span.start,
+                    Strand::Reverse => span.end,
+                })
+                .ok_or_else(invalid)?;
+            let offset = location
+                .offset_of(first_base, self.sequence.len())
+                .ok_or_else(invalid)?;
+            if let Some(offset) = offset.checked_sub(codon_start - 1) {
+                exceptions.push((offset / 3, amino_acid));
+            }
+        }
+
+        Ok(TranslationOptions {
+            genetic_code,
+            codon_start,
+            five_prime_partial: location.is_five_prime_partial(),
+            three_prime_partial: location.is_three_prime_partial(),
+            exceptions,
+        })
+    }
+
+    /// Translates a CDS from the record's nucleotide sequence.
+    pub fn translate_feature(&self, feature: &Feature) -> Result<Vec<u8>, TranslationError> {
+        let options = self.translation_options(feature)?;
+        let nucleotides = self.feature_sequence(feature)?;
+        Ok(translate(&nucleotides, &options))
+    }
+}
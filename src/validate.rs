@@ -0,0 +1,533 @@
+use crate::embl::is_id_line;
+use crate::translation::{translate, TranslationError};
+use crate::{
+    offset_in, parse_record, serialize_as_utf8, trim_ascii, Feature, FeatureType, LengthUnit,
+    ParseError, Sequence, Span,
+};
+use serde::Serialize;
+use std::fmt;
+
+/// At most this many differing residues are listed for a CDS; the rest are only counted.
+const MAX_REPORTED_MISMATCHES: usize = 10;
+
+/// A residue where the `/translation` and the translated nucleotides disagree.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ResidueMismatch {
+    /// 1-based position in the protein
+    pub position: usize,
+    pub annotated: char,
+    pub translated: char,
+}
+
+/// Something wrong with a CDS found by comparing its `/translation` to its nucleotides.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "issue", rename_all = "snake_case")]
+pub enum TranslationIssue {
+    /// the nucleotides couldn't be translated, e.g. the location refers to another record
+    Untranslatable {
+        reason: String,
+    },
+    /// the coding length after `/codon_start` isn't a multiple of three on a complete 3' end
+    Frame {
+        length: usize,
+        codon_start: usize,
+    },
+    /// the `/translation` matches when read from another `/codon_start`
+    CodonStart {
+        annotated: usize,
+        matching: usize,
+    },
+    /// 1-based positions of stop codons before the end of the CDS
+    InternalStops {
+        positions: Vec<usize>,
+    },
+    LengthMismatch {
+        annotated: usize,
+        translated: usize,
+    },
+    /// residues that differ, ignoring `X` on either side
+    Mismatches {
+        count: usize,
+        first: Vec<ResidueMismatch>,
+    },
+}
+
+impl fmt::Display for TranslationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TranslationIssue::Untranslatable { reason } => write!(f, "untranslatable: {}", reason),
+            TranslationIssue::Frame {
+                length,
+                codon_start,
+            } => write!(
+                f,
+                "coding length {} from codon_start {} is not a multiple of three",
+                length, codon_start
+            ),
+            TranslationIssue::CodonStart {
+                annotated,
+                matching,
+            } => write!(
+                f,
+                "translation matches codon_start {} rather than {}",
+                matching, annotated
+            ),
+            TranslationIssue::InternalStops { positions } => {
+                write!(f, "internal stop codons at residues {:?}", positions)
+            }
+            TranslationIssue::LengthMismatch {
+                annotated,
+                translated,
+            } => write!(
+                f,
+                "translation has {} residues but the CDS translates to {}",
+                annotated, translated
+            ),
+            TranslationIssue::Mismatches { count, first } => {
+                write!(f, "{} mismatched residues", count)?;
+                for mismatch in first {
+                    write!(
+                        f,
+                        " {}{}{}",
+                        mismatch.annotated, mismatch.position, mismatch.translated
+                    )?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The issues found in one CDS.
+#[derive(Debug, Serialize)]
+pub struct CdsReport {
+    #[serde(serialize_with = "serialize_as_utf8")]
+    pub protein_id: Vec<u8>,
+    #[serde(serialize_with = "serialize_as_utf8")]
+    pub location: Vec<u8>,
+    pub issues: Vec<TranslationIssue>,
+}
+
+/// Something inconsistent in a record as a whole.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "issue", rename_all = "snake_case")]
+pub enum RecordIssue {
+    /// the ORIGIN residue count differs from the LOCUS length
+    LengthMismatch {
+        declared: usize,
+        actual: usize,
+    },
+    /// an ORIGIN line whose position number doesn't follow from the residues before it;
+    /// `offset` is the byte offset of the line in the record
+    OriginNumbering {
+        offset: usize,
+        expected: usize,
+        found: Option<usize>,
+    },
+    /// characters in ORIGIN that aren't IUPAC codes, which parsing drops or keeps silently
+    InvalidCharacters {
+        count: usize,
+        characters: Vec<char>,
+        first_offset: usize,
+    },
+    InvalidLocation {
+        location: String,
+        reason: String,
+    },
+    /// a feature reaching past the LOCUS length
+    FeatureOutOfBounds {
+        location: String,
+        length: usize,
+    },
+}
+
+impl fmt::Display for RecordIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecordIssue::LengthMismatch { declared, actual } => write!(
+                f,
+                "LOCUS declares {} residues but ORIGIN has {}",
+                declared, actual
+            ),
+            RecordIssue::OriginNumbering {
+                offset,
+                expected,
+                found: Some(found),
+            } => write!(
+                f,
+                "ORIGIN line at offset {} is numbered {}, expected {}",
+                offset, found, expected
+            ),
+            RecordIssue::OriginNumbering {
+                offset,
+                expected,
+                found: None,
+            } => write!(
+                f,
+                "ORIGIN line at offset {} has no position number, expected {}",
+                offset, expected
+            ),
+            RecordIssue::InvalidCharacters {
+                count,
+                characters,
+                first_offset,
+            } => write!(
+                f,
+                "{} invalid characters {:?} in ORIGIN, the first at offset {}",
+                count, characters, first_offset
+            ),
+            RecordIssue::InvalidLocation { location, reason } => {
+                write!(f, "invalid location {}: {}", location, reason)
+            }
+            RecordIssue::FeatureOutOfBounds { location, length } => write!(
+                f,
+                "location {} is outside of a sequence of length {}",
+                location, length
+            ),
+        }
+    }
+}
+
+/// Result of validating one record; only CDS features with issues are listed.
+#[derive(Debug, Serialize)]
+pub struct ValidationReport {
+    /// the VERSION, or the LOCUS name if there is none
+    #[serde(serialize_with = "serialize_as_utf8")]
+    pub source_id: Vec<u8>,
+    pub issues: Vec<RecordIssue>,
+    /// number of CDS features with a `/translation` that were checked
+    pub checked_cds: usize,
+    pub cds: Vec<CdsReport>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty() && self.cds.is_empty()
+    }
+}
+
+/// Parses a GenBank or EMBL record and runs every check on it: the ORIGIN (or SQ) layout and
+/// characters, the LOCUS length, feature bounds and the CDS translations.
+pub fn validate_record(record: &[u8]) -> Result<ValidationReport, ParseError> {
+    let (sequence, _) = parse_record(record)?;
+    let mut report = sequence.validate_translations();
+    report.issues = check_origin(record, sequence.locus.unit, is_id_line(record));
+    report.issues.extend(sequence.check_consistency());
+    Ok(report)
+}
+
+#[inline]
+fn is_iupac_nucleotide(residue: u8) -> bool {
+    matches!(
+        residue.to_ascii_uppercase(),
+        b'A' | b'C'
+            | b'G'
+            | b'T'
+            | b'U'
+            | b'R'
+            | b'Y'
+            | b'S'
+            | b'W'
+            | b'K'
+            | b'M'
+            | b'B'
+            | b'D'
+            | b'H'
+            | b'V'
+            | b'N'
+    )
+}
+
+#[inline]
+fn is_iupac_amino_acid(residue: u8) -> bool {
+    // every letter is assigned, including B, Z, J and X for ambiguity, plus * for a stop
+    residue == b'*' || residue.is_ascii_alphabetic()
+}
+
+/// Checks the ORIGIN lines of a raw record: each starts with the 1-based position of its first
+/// residue, which with 60 residues per line is 1, 61, 121, ..., and holds only IUPAC codes.
+///
+/// The sequence lines after the SQ line of an `embl` record instead end with the position of
+/// their last residue, 60, 120, ...
+fn check_origin(record: &[u8], unit: LengthUnit, embl: bool) -> Vec<RecordIssue> {
+    let is_valid_residue = match unit {
+        LengthUnit::BasePairs => is_iupac_nucleotide,
+        LengthUnit::AminoAcids => is_iupac_amino_acid,
+    };
+
+    let mut issues = Vec::new();
+    let mut lines = record
+        .split(|&b| b == b'\n')
+        .skip_while(|line| {
+            if embl {
+                !line.starts_with(b"SQ")
+            } else {
+                !line.starts_with(b"ORIGIN")
+            }
+        })
+        .skip(1);
+
+    let mut residues: usize = 0;
+    // difference between the numbering and the residue count, once one has been reported
+    let mut shift = 0isize;
+    let mut invalid = Vec::new();
+    let mut first_offset = 0;
+    for line in &mut lines {
+        let trimmed = trim_ascii(line);
+        if trimmed.starts_with(b"//") {
+            break;
+        }
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        let offset = offset_in(record, line);
+        let (number, body) = if embl {
+            let digits = trimmed
+                .iter()
+                .rev()
+                .take_while(|b| b.is_ascii_digit())
+                .count();
+            let (body, number) = trimmed.split_at(trimmed.len() - digits);
+            (number, body)
+        } else {
+            let digits = trimmed.iter().take_while(|b| b.is_ascii_digit()).count();
+            let (number, body) = trimmed.split_at(digits);
+            (number, body)
+        };
+        let found = std::str::from_utf8(number)
+            .ok()
+            .and_then(|number| number.parse::<usize>().ok());
+        let before = residues;
+
+        let start = offset_in(record, body);
+        for (i, &residue) in body.iter().enumerate() {
+            if residue.is_ascii_whitespace() {
+                continue;
+            }
+            if is_valid_residue(residue) {
+                residues += 1;
+            } else {
+                if invalid.is_empty() {
+                    first_offset = start + i;
+                }
+                invalid.push(residue);
+            }
+        }
+
+        // the number of the first residue of the line, or of the last in EMBL
+        let numbered = if embl { residues } else { before + 1 };
+        let expected = numbered.saturating_add_signed(shift);
+        if found != Some(expected) {
+            issues.push(RecordIssue::OriginNumbering {
+                offset,
+                expected,
+                found,
+            });
+            if let Some(found) = found {
+                shift = found as isize - numbered as isize;
+            }
+        }
+    }
+
+    if !invalid.is_empty() {
+        let count = invalid.len();
+        invalid.sort_unstable();
+        invalid.dedup();
+        issues.push(RecordIssue::InvalidCharacters {
+            count,
+            characters: invalid.into_iter().map(char::from).collect(),
+            first_offset,
+        });
+    }
+    issues
+}
+
+#[inline]
+fn residues_match(annotated: u8, translated: u8) -> bool {
+    annotated == translated || annotated == b'X' || translated == b'X'
+}
+
+fn translations_match(annotated: &[u8], translated: &[u8]) -> bool {
+    annotated.len() == translated.len()
+        && annotated
+            .iter()
+            .zip(translated)
+            .all(|(&a, &t)| residues_match(a, t))
+}
+
+impl Sequence {
+    /// Checks the sequence against the LOCUS length and every feature location against the
+    /// bounds of the sequence.
+    pub fn check_consistency(&self) -> Vec<RecordIssue> {
+        let mut issues = Vec::new();
+        let length = self.locus.length;
+        // records without ORIGIN, e.g. CONTIG records, have no residues to count
+        if !self.sequence.is_empty() && self.sequence.len() != length {
+            issues.push(RecordIssue::LengthMismatch {
+                declared: length,
+                actual: self.sequence.len(),
+            });
+        }
+
+        for feature in &self.features {
+            let location = String::from_utf8_lossy(&feature.location).into_owned();
+            let spans = match feature.parsed_location() {
+                Ok(parsed) => parsed.spans(),
+                Err(error) => {
+                    issues.push(RecordIssue::InvalidLocation {
+                        location,
+                        reason: error.to_string(),
+                    });
+                    continue;
+                }
+            };
+            let in_bounds = |span: &Span| {
+                span.start >= 1
+                    && span.start <= length
+                    && span.end >= 1
+                    && span.end <= length
+                    && (span.start <= span.end || self.is_circular())
+            };
+            if !spans.iter().all(in_bounds) {
+                issues.push(RecordIssue::FeatureOutOfBounds { location, length });
+            }
+        }
+
+        issues
+    }
+
+    /// Translates every CDS that has a `/translation` and reports where the two disagree.
+    pub fn validate_translations(&self) -> ValidationReport {
+        // records without a VERSION line are identified by their LOCUS name
+        let source_id = if self.version.is_empty() {
+            &self.locus.name
+        } else {
+            &self.version
+        };
+        let mut report = ValidationReport {
+            source_id: source_id.clone(),
+            issues: Vec::new(),
+            checked_cds: 0,
+            cds: Vec::new(),
+        };
+        // without residues, e.g. in a CONTIG record, there is nothing to translate
+        if self.sequence.is_empty() {
+            return report;
+        }
+
+        for feature in &self.features {
+            if feature.feature_type != Some(FeatureType::Cds) {
+                continue;
+            }
+            let Some(annotated) = feature.qualifier(b"translation") else {
+                continue;
+            };
+
+            report.checked_cds += 1;
+            let issues = self.translation_issues(feature, annotated);
+            if !issues.is_empty() {
+                report.cds.push(CdsReport {
+                    protein_id: feature
+                        .qualifier(b"protein_id")
+                        .unwrap_or_default()
+                        .to_vec(),
+                    location: feature.location.clone(),
+                    issues,
+                });
+            }
+        }
+
+        report
+    }
+
+    fn translation_issues(&self, feature: &Feature, annotated: &[u8]) -> Vec<TranslationIssue> {
+        let untranslatable = |error: TranslationError| {
+            vec![TranslationIssue::Untranslatable {
+                reason: error.to_string(),
+            }]
+        };
+        let mut options = match self.translation_options(feature) {
+            Ok(options) => options,
+            Err(error) => return untranslatable(error),
+        };
+        let nucleotides = match self.feature_sequence(feature) {
+            Ok(nucleotides) => nucleotides,
+            Err(error) => return untranslatable(error.into()),
+        };
+
+        let mut issues = Vec::new();
+        let codon_start = options.codon_start;
+        let coding_length = nucleotides.len().saturating_sub(codon_start - 1);
+        // a stop completed by /transl_except, e.g. by polyadenylation, makes up the missing bases
+        let completed = options
+            .exceptions
+            .iter()
+            .any(|&(index, _)| index == coding_length / 3);
+        if coding_length % 3 != 0 && !options.three_prime_partial && !completed {
+            issues.push(TranslationIssue::Frame {
+                length: coding_length,
+                codon_start,
+            });
+        }
+
+        let translated = translate(&nucleotides, &options);
+        if translations_match(annotated, &translated) {
+            return issues;
+        }
+
+        let internal_stops: Vec<usize> = translated
+            .iter()
+            .enumerate()
+            .filter(|(_, &amino_acid)| amino_acid == b'*')
+            .map(|(i, _)| i + 1)
+            .collect();
+
+        for matching in (1..=3).filter(|&start| start != codon_start) {
+            options.codon_start = matching;
+            if translations_match(annotated, &translate(&nucleotides, &options)) {
+                issues.push(TranslationIssue::CodonStart {
+                    annotated: codon_start,
+                    matching,
+                });
+                return issues;
+            }
+        }
+
+        if !internal_stops.is_empty() {
+            issues.push(TranslationIssue::InternalStops {
+                positions: internal_stops,
+            });
+        }
+        if annotated.len() != translated.len() {
+            issues.push(TranslationIssue::LengthMismatch {
+                annotated: annotated.len(),
+                translated: translated.len(),
+            });
+        }
+
+        // stops are already reported above
+        let mismatches: Vec<ResidueMismatch> = annotated
+            .iter()
+            .zip(&translated)
+            .enumerate()
+            .filter(|(_, (&a, &t))| t != b'*' && !residues_match(a, t))
+            .map(|(i, (&a, &t))| ResidueMismatch {
+                position: i + 1,
+                annotated: a as char,
+                translated: t as char,
+            })
+            .collect();
+        if !mismatches.is_empty() {
+            issues.push(TranslationIssue::Mismatches {
+                count: mismatches.len(),
+                first: mismatches
+                    .into_iter()
+                    .take(MAX_REPORTED_MISMATCHES)
+                    .collect(),
+            });
+        }
+
+        issues
+    }
+}
@@ -0,0 +1,328 @@
+use crate::{trim_ascii, Feature, LengthUnit, Reference, Sequence};
+use std::io::{self, Write};
+
+const LINE_WIDTH: usize = 79;
+/// column where the data of header lines starts
+const HEADER_INDENT: usize = 12;
+/// column where feature locations and qualifiers start
+const QUALIFIER_INDENT: usize = 21;
+const RESIDUES_PER_LINE: usize = 60;
+const RESIDUES_PER_BLOCK: usize = 10;
+
+/// Qualifiers whose values are written without quotes, as NCBI does.
+const UNQUOTED_QUALIFIERS: [&[u8]; 13] = [
+    b"anticodon",
+    b"citation",
+    b"codon_start",
+    b"compare",
+    b"direction",
+    b"estimated_length",
+    b"mod_base",
+    b"number",
+    b"rpt_type",
+    b"rpt_unit_range",
+    b"tag_peptide",
+    b"transl_except",
+    b"transl_table",
+];
+
+/// Writes `sequence` as a GenBank flat file record, ending with its `//` line.
+///
+/// Everything `parse_sequence_record` keeps is written, so parsing the output gives the same
+/// record back. Sections the parser drops, such as SOURCE, BASE COUNT and CONTIG, are left out
+/// or, for SOURCE, filled in from the organism.
+pub fn write_genbank<W: Write>(mut writer: W, sequence: &Sequence) -> io::Result<()> {
+    writer.write_all(&locus_line(sequence))?;
+    writer.write_all(b"\n")?;
+
+    write_field(&mut writer, "DEFINITION", &sequence.definition)?;
+    let mut accessions = sequence.accession.clone();
+    for secondary in &sequence.secondary_accessions {
+        accessions.push(b' ');
+        accessions.extend_from_slice(secondary);
+    }
+    write_field(&mut writer, "ACCESSION", &accessions)?;
+    write_field(&mut writer, "VERSION", &sequence.version)?;
+    for (i, dblink) in sequence.dblinks.iter().enumerate() {
+        let mut line = format!("{}: ", dblink.database).into_bytes();
+        line.extend_from_slice(&dblink.ids.join(&b", "[..]));
+        write_field(&mut writer, if i == 0 { "DBLINK" } else { "" }, &line)?;
+    }
+    write_field(&mut writer, "PROJECT", &sequence.project)?;
+    let mut keywords = sequence.keywords.join(&b"; "[..]);
+    keywords.push(b'.');
+    write_field(&mut writer, "KEYWORDS", &keywords)?;
+    if let Some((number, total)) = sequence.segment {
+        write_field(
+            &mut writer,
+            "SEGMENT",
+            format!("{} of {}", number, total).as_bytes(),
+        )?;
+    }
+
+    // the organism keeps the separator before the taxonomy lines, which parsing adds back
+    let organism = sequence.organism.trim_ascii_end();
+    write_field(&mut writer, "SOURCE", organism)?;
+    write_field(&mut writer, "  ORGANISM", organism)?;
+    write_field(&mut writer, "", &sequence.taxonomy)?;
+
+    for reference in &sequence.references {
+        write_reference(&mut writer, reference, sequence.locus.unit)?;
+    }
+
+    // COMMENT lines are written as they are, wrapping them would add line breaks
+    for (i, line) in sequence.comment.split(|&b| b == b'\n').enumerate() {
+        if sequence.comment.is_empty() {
+            break;
+        }
+        let keyword = if i == 0 { "COMMENT" } else { "" };
+        write!(writer, "{:<HEADER_INDENT$}", keyword)?;
+        writer.write_all(line)?;
+        writer.write_all(b"\n")?;
+    }
+
+    if !sequence.features.is_empty() {
+        writeln!(
+            writer,
+            "{:<QUALIFIER_INDENT$}Location/Qualifiers",
+            "FEATURES"
+        )?;
+        for feature in &sequence.features {
+            write_feature(&mut writer, feature)?;
+        }
+    }
+
+    // records without a sequence, e.g. CONTIG records, have no ORIGIN
+    if !sequence.sequence.is_empty() {
+        writeln!(writer, "{:<HEADER_INDENT$}", "ORIGIN")?;
+        for (i, line) in sequence.sequence.chunks(RESIDUES_PER_LINE).enumerate() {
+            write!(writer, "{:>9}", i * RESIDUES_PER_LINE + 1)?;
+            for block in line.chunks(RESIDUES_PER_BLOCK) {
+                writer.write_all(b" ")?;
+                writer.write_all(block)?;
+            }
+            writer.write_all(b"\n")?;
+        }
+    }
+    writer.write_all(b"//\n")
+}
+
+/// Pads `line` with spaces up to `column`, or adds one space if it already reaches it.
+#[inline]
+fn pad_to(line: &mut Vec<u8>, column: usize) {
+    if line.len() < column {
+        line.resize(column, b' ');
+    } else if line.last() != Some(&b' ') {
+        line.push(b' ');
+    }
+}
+
+/// The LOCUS line in the fixed column layout, e.g.
+/// `LOCUS       AB000048                2007 bp    DNA     linear   VRL 14-JUL-2009`
+fn locus_line(sequence: &Sequence) -> Vec<u8> {
+    let locus = &sequence.locus;
+    let mut line = b"LOCUS       ".to_vec();
+    line.extend_from_slice(&locus.name);
+    pad_to(&mut line, 29);
+    line.extend_from_slice(format!("{:>11} {}", locus.length, locus.unit).as_bytes());
+
+    // the strandedness (`ss-`, `ds-`, `ms-`) goes three columns before the molecule type
+    let stranded = locus.molecule_type.get(2) == Some(&b'-');
+    pad_to(&mut line, if stranded { 44 } else { 47 });
+    line.extend_from_slice(&locus.molecule_type);
+    pad_to(&mut line, 55);
+    if let Some(topology) = locus.topology {
+        line.extend_from_slice(topology.to_string().as_bytes());
+    }
+    pad_to(&mut line, 64);
+    line.extend_from_slice(&locus.division);
+    pad_to(&mut line, 68);
+    if let Some(date) = locus.date {
+        line.extend_from_slice(
+            date.format("%d-%b-%Y")
+                .to_string()
+                .to_uppercase()
+                .as_bytes(),
+        );
+    }
+
+    line.truncate(line.trim_ascii_end().len());
+    line
+}
+
+/// Writes a header field, `keyword` followed by `text` wrapped at the line width. Nothing is
+/// written for empty text; an empty `keyword` continues the previous field.
+fn write_field<W: Write>(writer: &mut W, keyword: &str, text: &[u8]) -> io::Result<()> {
+    let text = trim_ascii(text);
+    for (i, line) in wrap(text, LINE_WIDTH - HEADER_INDENT, false)
+        .into_iter()
+        .enumerate()
+    {
+        write!(
+            writer,
+            "{:<HEADER_INDENT$}",
+            if i == 0 { keyword } else { "" }
+        )?;
+        writer.write_all(line)?;
+        writer.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+fn write_reference<W: Write>(
+    writer: &mut W,
+    reference: &Reference,
+    unit: LengthUnit,
+) -> io::Result<()> {
+    let mut line = reference.number.to_string();
+    if !reference.bases.is_empty() {
+        let ranges: Vec<String> = reference
+            .bases
+            .iter()
+            .map(|(start, end)| format!("{} to {}", start, end))
+            .collect();
+        let unit = match unit {
+            LengthUnit::BasePairs => "bases",
+            LengthUnit::AminoAcids => "residues",
+        };
+        line = format!("{:<3}({} {})", line, unit, ranges.join("; "));
+    }
+    write_field(writer, "REFERENCE", line.as_bytes())?;
+
+    // `a, b and c`, which is split up again on parsing
+    let mut authors = Vec::new();
+    for (i, author) in reference.authors.iter().enumerate() {
+        if i > 0 {
+            let separator: &[u8] = if i + 1 == reference.authors.len() {
+                b" and "
+            } else {
+                b", "
+            };
+            authors.extend_from_slice(separator);
+        }
+        authors.extend_from_slice(author);
+    }
+    write_field(writer, "  AUTHORS", &authors)?;
+    write_field(writer, "  CONSRTM", &reference.consortium)?;
+    write_field(writer, "  TITLE", &reference.title)?;
+    write_field(writer, "  JOURNAL", &reference.journal)?;
+    write_field(writer, "   PUBMED", &reference.pubmed)?;
+    write_field(writer, "  REMARK", &reference.remark)
+}
+
+fn write_feature<W: Write>(writer: &mut W, feature: &Feature) -> io::Result<()> {
+    let key = feature
+        .feature_type
+        .as_ref()
+        .map(ToString::to_string)
+        .unwrap_or_default();
+    let width = LINE_WIDTH - QUALIFIER_INDENT;
+    for (i, line) in wrap_location(&feature.location, width)
+        .into_iter()
+        .enumerate()
+    {
+        if i == 0 {
+            write!(writer, "     {:<16}", key)?;
+        } else {
+            write!(writer, "{:QUALIFIER_INDENT$}", "")?;
+        }
+        writer.write_all(line)?;
+        writer.write_all(b"\n")?;
+    }
+
+    for (name, value) in &feature.qualifiers {
+        let mut qualifier = b"/".to_vec();
+        qualifier.extend_from_slice(name);
+        if !value.is_empty() {
+            qualifier.push(b'=');
+            if UNQUOTED_QUALIFIERS.contains(&name.as_slice()) {
+                qualifier.extend_from_slice(value);
+            } else {
+                // quotes inside a value are escaped by doubling them
+                qualifier.push(b'"');
+                for &b in value {
+                    if b == b'"' {
+                        qualifier.push(b'"');
+                    }
+                    qualifier.push(b);
+                }
+                qualifier.push(b'"');
+            }
+        }
+
+        // a word longer than a line, such as a URL in a note, is split as NCBI does; parsing
+        // drops the spaces from translations, but puts one in other values
+        for line in wrap(&qualifier, width, true) {
+            write!(writer, "{:QUALIFIER_INDENT$}", "")?;
+            writer.write_all(line)?;
+            writer.write_all(b"\n")?;
+        }
+    }
+    Ok(())
+}
+
+/// Splits `text` into lines of at most `width` bytes at spaces, dropping the space at each
+/// break, which parsing puts back when it joins the lines.
+///
+/// No line starts with `/`, which would read as a new qualifier. A word longer than `width` is
+/// split if `split_words` and otherwise left to run over.
+fn wrap(text: &[u8], width: usize, split_words: bool) -> Vec<&[u8]> {
+    let mut lines = Vec::new();
+    let mut rest = text;
+    while rest.len() > width {
+        let is_break = |(i, &b): (usize, &u8)| {
+            i > 0 && b == b' ' && rest.get(i + 1).is_some_and(|&next| next != b'/')
+        };
+        let space = rest[..=width]
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|&item| is_break(item))
+            .map(|(i, _)| i);
+        match space {
+            Some(space) => {
+                lines.push(&rest[..space]);
+                rest = &rest[space + 1..];
+            }
+            None if split_words => {
+                lines.push(&rest[..width]);
+                rest = &rest[width..];
+            }
+            None => match rest
+                .iter()
+                .enumerate()
+                .skip(width)
+                .find(|&item| is_break(item))
+            {
+                Some((space, _)) => {
+                    lines.push(&rest[..space]);
+                    rest = &rest[space + 1..];
+                }
+                None => break,
+            },
+        }
+    }
+    if !rest.is_empty() {
+        lines.push(rest);
+    }
+    lines
+}
+
+/// Splits a location into lines of at most `width` bytes after its commas, or anywhere if a
+/// part is longer than that; parsing removes the line breaks again.
+fn wrap_location(location: &[u8], width: usize) -> Vec<&[u8]> {
+    let mut lines = Vec::new();
+    let mut rest = location;
+    while rest.len() > width {
+        let end = rest[..width]
+            .iter()
+            .rposition(|&b| b == b',')
+            .map_or(width, |comma| comma + 1);
+        lines.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+    if !rest.is_empty() || lines.is_empty() {
+        lines.push(rest);
+    }
+    lines
+}
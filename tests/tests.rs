@@ -1,6 +1,14 @@
+use chrono::NaiveDate;
 use genbank_parser::{
-    parse_sequence_record, parse_sequence_record_by_positions, split_on_delimiter,
+    decompress, parse_embl_record, parse_record, parse_sequence_record,
+    parse_sequence_record_by_positions, reverse_complement, split_on_delimiter, translate,
+    validate_record, write_bed, write_bed12, write_genbank, write_gff3, write_gff3_sequence,
+    write_gtf, DbLinkDatabase, DeflineError, ExtractError, Fasta, FeatureType, GapLength,
+    GenbankReader, GeneticCode, LengthUnit, Location, ParseError, Pipeline, Position, RecordIssue,
+    ResidueMismatch, Topology, TranslationIssue, TranslationOptions, GFF3_FASTA_DIRECTIVE,
+    GFF3_HEADER,
 };
+use std::io::{BufReader, Write};
 
 const RECORD: &[u8] =
     b"LOCUS       AB000048                2007 bp    DNA     linear   VRL 14-JUL-2009
@@ -181,6 +189,75 @@ ORIGIN
       661 cggcctttct acagggactc tccgctgggg actctccagg gaggcgtggc ctcggcggga
       721 ctcgggagtg gcgagcctca gatgctgcat ataa";
 
+/// RECORD_3 as ENA writes it
+const EMBL_RECORD: &[u8] = b"ID   AF219750; SV 1; linear; genomic DNA; STD; VRL; 754 BP.
+XX
+AC   AF219750;
+XX
+DT   12-JAN-2000 (Rel. 62, Created)
+DT   02-JAN-2001 (Rel. 66, Last updated, Version 2)
+XX
+DE   HIV-1 LTS 38d from Australia nef protein (nef) gene, complete cds.
+XX
+KW   .
+XX
+OS   Human immunodeficiency virus 1 (HIV-1)
+OC   Viruses; Riboviria; Pararnavirae; Artverviricota; Revtraviricetes;
+OC   Ortervirales; Retroviridae; Orthoretrovirinae; Lentivirus.
+XX
+RN   [1]
+RP   1-754
+RA   Ashton L., Rhodes D., Solomon A., Deacon N., Satchell C., Carr A.,
+RA   Cooper D., Biti R., Stewart G., Kaldor J.;
+RT   \"Viral diversity in the nef/LTR region of the HIV-1 genome: associations
+RT   with long-term nonprogression\";
+RL   Unpublished.
+XX
+RN   [2]
+RP   1-754
+RA   Rhodes D.;
+RT   ;
+RL   Submitted (23-DEC-1999) to the INSDC.
+RL   AIDS Molecular Biology Unit, Macfarlane Burnet Centre for Medical
+RL   Research, Yarra Bend Rd., Fairfield, Victoria 3078, Australia
+XX
+FH   Key             Location/Qualifiers
+FH
+FT   source          1..754
+FT                   /organism=\"Human immunodeficiency virus 1\"
+FT                   /proviral
+FT                   /mol_type=\"genomic DNA\"
+FT                   /isolate=\"LTS 38d\"
+FT                   /db_xref=\"taxon:11676\"
+FT                   /country=\"Australia\"
+FT   gene            1..657
+FT                   /gene=\"nef\"
+FT   CDS             1..657
+FT                   /gene=\"nef\"
+FT                   /codon_start=1
+FT                   /product=\"nef protein\"
+FT                   /protein_id=\"AAG44221.1\"
+FT                   /translation=\"MGGKWSKRSEDRWSTIRERMRRAPAAEPAADGVGAASRDLEKYGAITSSNT
+FT                   AATNADCAWLEAQEEEEEVGFPVRPQVPLRPMTWKAALDLSHFLKEKGGLEGLVYSQKR
+FT                   RDILDLWIYHTQGFFPDWQNYTPGPGTRFPLTFGWCFKLVPMEREKIEEANEGENNSLL
+FT                   HPLSQHGMDDPEREVLVWKFDSRLAFHHVARELHPGVLQDLMTPSFYNC\"
+XX
+SQ   Sequence 754 BP; 216 A; 166 C; 219 G; 153 T; 0 other;
+     atgggtggca agtggtcaaa acgtagcgag gatagatggt ctaccataag ggaaagaatg        60
+     agacgtgcgc cagcagctga gccagcagca gatggggtgg gagcagcatc tcgagacttg       120
+     gaaaaatatg gcgcaatcac aagtagcaat acagcagcta ccaatgctga ttgtgcctgg       180
+     ctagaagcac aagaagagga ggaggaggtg ggctttccag tcagacctca agtaccttta       240
+     agaccaatga cctggaaggc agctttagat cttagccact ttttaaaaga aaagggggga       300
+     ctggaagggc tagtttactc ccaaaaaaga cgagatatcc ttgatttgtg gatctaccac       360
+     acacaaggct tcttccctga ttggcaaaac tacacaccag ggccagggac cagatttcca       420
+     ctgacctttg ggtggtgctt caagttggta ccaatggagc gagagaaaat agaagaggcc       480
+     aatgaaggag agaacaacag tttgttacac cctttaagcc agcatgggat ggatgacccg       540
+     gagagagaag tgttagtgtg gaagtttgac agccgcctag catttcatca cgtggctcga       600
+     gagctgcatc ccggagtact acaagatctg atgacaccga gcttctacaa ctgctgacat       660
+     cggcctttct acagggactc tccgctgggg actctccagg gaggcgtggc ctcggcggga       720
+     ctcgggagtg gcgagcctca gatgctgcat ataa                                   754
+//";
+
 #[test]
 #[ignore]
 fn test_parse_sequence_record_by_positions() {
@@ -199,7 +276,7 @@ fn test_parse_sequence_record_by_positions() {
 #[test]
 #[ignore]
 fn test_parse_sequence_record() {
-    let (sequence, _) = parse_sequence_record(RECORD);
+    let (sequence, _) = parse_sequence_record(RECORD).unwrap();
     println!(
         "organism: {:?}",
         String::from_utf8_lossy(&sequence.organism)
@@ -221,7 +298,7 @@ fn test_parse_sequence_record() {
 #[test]
 #[ignore]
 fn test_parse_sequence_record_2() {
-    let (sequence, _) = parse_sequence_record(RECORD_2);
+    let (sequence, _) = parse_sequence_record(RECORD_2).unwrap();
     println!(
         "organism: {:?}",
         String::from_utf8_lossy(&sequence.organism)
@@ -247,7 +324,7 @@ fn test_parse_sequence_record_2() {
 
 #[test]
 fn test_parse_sequence_record_3() {
-    let (sequence, _) = parse_sequence_record(RECORD_3);
+    let (sequence, _) = parse_sequence_record(RECORD_3).unwrap();
     // println!("sequence: {:?}", &sequence);
     // println!(
     //     "features: {:?}",
@@ -265,6 +342,1742 @@ fn test_parse_sequence_record_3() {
     assert_eq!(sequence.mol_type, b"genomic DNA");
 }
 
+#[test]
+fn test_parse_locus() {
+    let (sequence, _) = parse_sequence_record(RECORD_2).unwrap();
+    let locus = sequence.locus;
+    assert_eq!(locus.name, b"AF148865");
+    assert_eq!(locus.length, 81);
+    assert_eq!(locus.unit, LengthUnit::BasePairs);
+    assert_eq!(locus.molecule_type, b"RNA");
+    assert_eq!(locus.topology, Some(Topology::Linear));
+    assert_eq!(locus.division, b"VRL");
+    assert_eq!(locus.date, NaiveDate::from_ymd_opt(2016, 7, 26));
+
+    // older whitespace-separated layout without a topology
+    let record = b"LOCUS       HUMCYCLOX    1263 bp ss-mRNA         PRI       14-JAN-1995
+ORIGIN      
+        1 acgtacgtac";
+    let locus = parse_sequence_record(record).unwrap().0.locus;
+    assert_eq!(locus.name, b"HUMCYCLOX");
+    assert_eq!(locus.length, 1263);
+    assert_eq!(locus.molecule_type, b"ss-mRNA");
+    assert_eq!(locus.topology, None);
+    assert_eq!(locus.division, b"PRI");
+    assert_eq!(locus.date, NaiveDate::from_ymd_opt(1995, 1, 14));
+
+    // locus name running into the length column
+    let record = b"LOCUS       NC_0123456789012312345678901 bp    DNA     circular BCT 01-FEB-2020
+ORIGIN      
+        1 acgtacgtac";
+    let locus = parse_sequence_record(record).unwrap().0.locus;
+    assert_eq!(locus.name, b"NC_0123456789012");
+    assert_eq!(locus.length, 12345678901);
+    assert_eq!(locus.topology, Some(Topology::Circular));
+    assert_eq!(locus.division, b"BCT");
+
+    // a division from before the current set, and a stray token, don't fail the record
+    let record = b"LOCUS       HUMHBB      73308 bp ds-DNA             HUM       15-JUN-1990 X
+ORIGIN      
+        1 acgtacgtac";
+    let locus = parse_sequence_record(record).unwrap().0.locus;
+    assert_eq!(locus.name, b"HUMHBB");
+    assert_eq!(locus.length, 73308);
+    assert_eq!(locus.molecule_type, b"ds-DNA");
+    assert_eq!(locus.division, b"HUM");
+    assert_eq!(locus.date, NaiveDate::from_ymd_opt(1990, 6, 15));
+
+    let record = b"LOCUS       AF000005    10    DNA     linear   VRL 26-JUL-2016
+ORIGIN      
+        1 acgtacgtac";
+    assert!(matches!(
+        parse_sequence_record(record),
+        Err(ParseError::InvalidLocus { .. })
+    ));
+
+    let record = b"LOCUS       AF000004    ten bp    DNA     linear   VRL 26-JUL-2016
+ORIGIN      
+        1 acgtacgtac";
+    assert!(matches!(
+        parse_sequence_record(record),
+        Err(ParseError::InvalidLocus { .. })
+    ));
+}
+
+#[test]
+fn test_parse_accessions() {
+    let (sequence, _) = parse_sequence_record(RECORD).unwrap();
+    assert_eq!(sequence.accession, b"AB000048");
+    assert!(sequence.secondary_accessions.is_empty());
+
+    let record = b"LOCUS       AE000001                  10 bp    DNA     linear   BCT 26-JUL-2016
+ACCESSION   AE000001 U00096 AE000111-AE000113
+            AF000009-AF000008 A00001-A99999
+VERSION     AE000001.1
+ORIGIN      
+        1 acgtacgtac";
+    let (sequence, _) = parse_sequence_record(record).unwrap();
+    assert_eq!(sequence.accession, b"AE000001");
+    assert_eq!(
+        sequence.secondary_accessions,
+        Vec::from([
+            b"U00096".to_vec(),
+            b"AE000111".to_vec(),
+            b"AE000112".to_vec(),
+            b"AE000113".to_vec(),
+            b"AF000009-AF000008".to_vec(),
+            b"A00001-A99999".to_vec(),
+        ])
+    );
+    assert_eq!(sequence.version, b"AE000001.1");
+
+    let record = b"LOCUS       AE000002                  10 bp    DNA     linear   BCT 26-JUL-2016
+ACCESSION   AE000002 REGION: 1..10
+ORIGIN      
+        1 acgtacgtac";
+    let (sequence, _) = parse_sequence_record(record).unwrap();
+    assert_eq!(sequence.accession, b"AE000002");
+    assert!(sequence.secondary_accessions.is_empty());
+}
+
+#[test]
+fn test_parse_references() {
+    let (sequence, _) = parse_sequence_record(RECORD_2).unwrap();
+    assert_eq!(sequence.references.len(), 2);
+
+    let reference = &sequence.references[0];
+    assert_eq!(reference.number, 1);
+    assert_eq!(reference.bases, Vec::from([(1, 81)]));
+    assert_eq!(
+        reference.authors,
+        Vec::from([
+            b"Gonin,P.".to_vec(),
+            b"Couillard,M.".to_vec(),
+            b"d'Halewyn,M.A.".to_vec(),
+        ])
+    );
+    assert_eq!(
+        reference.title,
+        b"Genetic diversity and molecular epidemiology of Norwalk-like viruses"
+    );
+    assert_eq!(
+        reference.journal,
+        b"J. Infect. Dis. 182 (3), 691-697 (2000)"
+    );
+    assert_eq!(reference.pubmed, b"10950761");
+
+    let reference = &sequence.references[1];
+    assert_eq!(reference.number, 2);
+    assert_eq!(reference.authors.len(), 2);
+    assert_eq!(reference.title, b"Direct Submission");
+    assert_eq!(reference.journal, b"Submitted (06-MAY-1999) Biologie Moleculaire-Virologie, Laboratoire de Sante Publique du Quebec, 20045 Chemin Sainte-Marie, Sainte-Anne-de-Bellevue, PQ H9X 3R5, Canada");
+    assert!(reference.pubmed.is_empty());
+
+    // reference without a base range
+    let (sequence, _) = parse_sequence_record(RECORD).unwrap();
+    assert_eq!(sequence.references[0].number, 1);
+    assert!(sequence.references[0].bases.is_empty());
+
+    let record = b"LOCUS       AF000005                  10 bp    DNA     linear   VRL 26-JUL-2016
+REFERENCE   3  (bases 1 to 4; 7 to
+            10)
+  CONSRTM   The Example Consortium
+  TITLE     Direct Submission
+  JOURNAL   Unpublished
+  REMARK    Erratum:[J. Virol. 1 (1), 2 (2000)]
+ORIGIN      
+        1 acgtacgtac";
+    let (sequence, _) = parse_sequence_record(record).unwrap();
+    let reference = &sequence.references[0];
+    assert_eq!(reference.number, 3);
+    assert_eq!(reference.bases, Vec::from([(1, 4), (7, 10)]));
+    assert!(reference.authors.is_empty());
+    assert_eq!(reference.consortium, b"The Example Consortium");
+    assert_eq!(reference.remark, b"Erratum:[J. Virol. 1 (1), 2 (2000)]");
+}
+
+#[test]
+fn test_parse_comment() {
+    let record = b"LOCUS       MN908947                  10 bp    RNA     linear   VRL 18-MAR-2020
+COMMENT     On Jan 17, 2020 this sequence version replaced MN908947.2.
+
+            ##Assembly-Data-START##
+            Assembly Method       :: Megahit v. V1.1.3
+            Sequencing Technology :: Illumina
+            ##Assembly-Data-END##
+            ##Genome-Assembly-Data-START##
+            Coverage              :: 
+            Genome Representation :: Full; consensus of
+                                     several runs
+            ##Genome-Assembly-Data-END##
+ORIGIN      
+        1 acgtacgtac";
+    let (sequence, _) = parse_sequence_record(record).unwrap();
+    assert!(sequence
+        .comment
+        .starts_with(b"On Jan 17, 2020 this sequence version replaced MN908947.2.\n\n##Assembly"));
+    assert!(sequence.comment.ends_with(b"##Genome-Assembly-Data-END##"));
+
+    assert_eq!(sequence.structured_comments.len(), 2);
+    let assembly = &sequence.structured_comments[0];
+    assert_eq!(assembly.name, b"Assembly-Data");
+    assert_eq!(
+        assembly.get(b"Assembly Method"),
+        Some(b"Megahit v. V1.1.3".as_slice())
+    );
+    assert_eq!(
+        assembly.get(b"Sequencing Technology"),
+        Some(b"Illumina".as_slice())
+    );
+
+    let genome_assembly = &sequence.structured_comments[1];
+    assert_eq!(genome_assembly.get(b"Coverage"), Some(b"".as_slice()));
+    assert_eq!(
+        genome_assembly.get(b"Genome Representation"),
+        Some(b"Full; consensus of several runs".as_slice())
+    );
+    assert_eq!(genome_assembly.get(b"Assembly Method"), None);
+}
+
+#[test]
+fn test_parse_dblink_project_keywords_segment() {
+    let record = b"LOCUS       CY121680                  10 bp    RNA     linear   VRL 26-JUL-2016
+ACCESSION   CY121680
+VERSION     CY121680.1
+DBLINK      BioProject: PRJNA485481
+            BioSample: SAMN09786626
+            Sequence Read Archive: SRR7694021, SRR7694022,
+            SRR7694023
+            ProbeDB: 123
+            Foo: bar
+PROJECT     GenomeProject:37631
+KEYWORDS    RefSeq; complete
+            genome.
+SEGMENT     4 of 8
+ORIGIN      
+        1 acgtacgtac";
+    let (sequence, _) = parse_sequence_record(record).unwrap();
+
+    assert_eq!(sequence.dblinks.len(), 5);
+    assert_eq!(sequence.dblinks[0].database, DbLinkDatabase::BioProject);
+    assert_eq!(
+        sequence.dblinks[0].ids,
+        Vec::from([b"PRJNA485481".to_vec()])
+    );
+    assert_eq!(sequence.dblinks[1].database, DbLinkDatabase::BioSample);
+    assert_eq!(
+        sequence.dblinks[2].database,
+        DbLinkDatabase::SequenceReadArchive
+    );
+    assert_eq!(
+        sequence.dblinks[2].ids,
+        Vec::from([
+            b"SRR7694021".to_vec(),
+            b"SRR7694022".to_vec(),
+            b"SRR7694023".to_vec(),
+        ])
+    );
+    assert_eq!(sequence.dblinks[3].database, DbLinkDatabase::ProbeDb);
+    assert_eq!(
+        sequence.dblinks[4].database,
+        DbLinkDatabase::Other(String::from("Foo"))
+    );
+
+    assert_eq!(sequence.project, b"GenomeProject:37631");
+    assert_eq!(
+        sequence.keywords,
+        Vec::from([b"RefSeq".to_vec(), b"complete genome".to_vec()])
+    );
+    assert_eq!(sequence.segment, Some((4, 8)));
+
+    let (sequence, _) = parse_sequence_record(RECORD).unwrap();
+    assert!(sequence.dblinks.is_empty());
+    assert!(sequence.keywords.is_empty());
+    assert_eq!(sequence.segment, None);
+}
+
+#[test]
+fn test_parse_feature_table() {
+    let (sequence, proteins) = parse_sequence_record(RECORD_3).unwrap();
+    let features = &sequence.features;
+    assert_eq!(features.len(), 3);
+
+    assert_eq!(features[0].feature_type, Some(FeatureType::Source));
+    assert_eq!(features[0].location, b"1..754");
+    let names: Vec<&[u8]> = features[0]
+        .qualifiers
+        .iter()
+        .map(|(name, _)| name.as_slice())
+        .collect();
+    assert_eq!(
+        names,
+        Vec::from([
+            b"organism".as_slice(),
+            b"proviral",
+            b"mol_type",
+            b"isolate",
+            b"db_xref",
+            b"country"
+        ])
+    );
+    assert_eq!(features[0].qualifier(b"proviral"), Some(b"".as_slice()));
+    assert_eq!(
+        features[0].qualifier(b"isolate"),
+        Some(b"LTS 38d".as_slice())
+    );
+
+    assert_eq!(features[1].feature_type, Some(FeatureType::Gene));
+    assert_eq!(features[1].qualifier(b"gene"), Some(b"nef".as_slice()));
+
+    assert_eq!(features[2].feature_type, Some(FeatureType::Cds));
+    assert_eq!(
+        features[2].qualifier(b"translation"),
+        Some(proteins[0].sequence.as_slice())
+    );
+    assert!(proteins[0]
+        .sequence
+        .starts_with(b"MGGKWSKRSEDRWSTIRERMRRAPAAEPAADGVGAASRDLEKYGAITSS"));
+    assert!(proteins[0].sequence.ends_with(b"LQDLMTPSFYNC"));
+
+    let record = b"LOCUS       AF000006                  10 bp    DNA     linear   VRL 26-JUL-2016
+FEATURES             Location/Qualifiers
+     misc_feature    join(1..2,
+                     5..10)
+                     /note=\"a=b and \"\"quoted\"\" text that wraps
+                     onto a second line\"
+                     /db_xref=\"taxon:1\"
+                     /db_xref=\"GeneID:2\"
+ORIGIN      
+        1 acgtacgtac";
+    let (sequence, _) = parse_sequence_record(record).unwrap();
+    let feature = &sequence.features[0];
+    assert_eq!(feature.location, b"join(1..2,5..10)");
+    assert_eq!(
+        feature.qualifier(b"note"),
+        Some(b"a=b and \"quoted\" text that wraps onto a second line".as_slice())
+    );
+    assert_eq!(
+        feature.qualifiers_named(b"db_xref").collect::<Vec<_>>(),
+        Vec::from([b"taxon:1".as_slice(), b"GeneID:2"])
+    );
+}
+
+#[test]
+fn test_feature_type_round_trip() {
+    for key in [
+        "CDS",
+        "mRNA",
+        "rRNA",
+        "tRNA",
+        "mat_peptide",
+        "sig_peptide",
+        "misc_feature",
+        "repeat_region",
+        "regulatory",
+        "D-loop",
+        "5'UTR",
+        "3'UTR",
+        "promoter",
+        "LTR",
+    ] {
+        let feature_type: FeatureType = key.parse().unwrap();
+        assert_eq!(feature_type.to_string(), key);
+    }
+    assert_eq!("mRNA".parse::<FeatureType>().unwrap(), FeatureType::MRna);
+    assert_eq!(
+        "5'UTR".parse::<FeatureType>().unwrap(),
+        FeatureType::FivePrimeUtr
+    );
+    assert_eq!(
+        "promoter".parse::<FeatureType>().unwrap(),
+        FeatureType::Unknown(String::from("promoter"))
+    );
+
+    let record = b"LOCUS       AF000007                  10 bp    DNA     linear   VRL 26-JUL-2016
+FEATURES             Location/Qualifiers
+     mat_peptide     1..6
+     LTR             7..10
+ORIGIN      
+        1 acgtacgtac";
+    let (sequence, _) = parse_sequence_record(record).unwrap();
+    assert_eq!(
+        sequence.features[0].feature_type,
+        Some(FeatureType::MatPeptide)
+    );
+    assert_eq!(
+        sequence.features[1].feature_type,
+        Some(FeatureType::Unknown(String::from("LTR")))
+    );
+}
+
+#[test]
+fn test_parse_location() {
+    let location: Location = "join(complement(100..200),<1..>50)".parse().unwrap();
+    assert_eq!(
+        location,
+        Location::Join(Vec::from([
+            Location::Complement(Box::new(Location::Range(
+                Position::Exact(100),
+                Position::Exact(200)
+            ))),
+            Location::Range(Position::Before(1), Position::After(50)),
+        ]))
+    );
+
+    assert_eq!(
+        "467".parse::<Location>().unwrap(),
+        Location::Single(Position::Exact(467))
+    );
+    assert_eq!(
+        "123^124".parse::<Location>().unwrap(),
+        Location::Between(123, 124)
+    );
+    assert_eq!(
+        "102.110".parse::<Location>().unwrap(),
+        Location::Single(Position::Within(102, 110))
+    );
+    assert_eq!(
+        "gap(unk100)".parse::<Location>().unwrap(),
+        Location::Gap(GapLength::Unknown(100))
+    );
+    assert_eq!(
+        "J00194.1:100..202".parse::<Location>().unwrap(),
+        Location::Remote {
+            accession: String::from("J00194.1"),
+            location: Box::new(Location::Range(Position::Exact(100), Position::Exact(202))),
+        }
+    );
+
+    // Display writes back the canonical string
+    for location in [
+        "join(complement(100..200),<1..>50)",
+        "complement(join(2691..4571,4918..5163))",
+        "order(1..10,20..30)",
+        "bond(12,63)",
+        "join(1..100,gap(),gap(50),J00194.1:100..202)",
+        "(23.45)..600",
+        "102.110",
+        "123^124",
+        "<1",
+    ] {
+        assert_eq!(location.parse::<Location>().unwrap().to_string(), location);
+    }
+    assert_eq!(
+        "join( 1..2 ,\n 5..10 )"
+            .parse::<Location>()
+            .unwrap()
+            .to_string(),
+        "join(1..2,5..10)"
+    );
+
+    let error = "join(1..2,5..)".parse::<Location>().unwrap_err();
+    assert_eq!(error.offset, 13);
+    assert!("join(1..2".parse::<Location>().is_err());
+    assert!("reverse(1..2)".parse::<Location>().is_err());
+    assert!("1..2)".parse::<Location>().is_err());
+
+    let (sequence, _) = parse_sequence_record(RECORD_2).unwrap();
+    assert_eq!(
+        sequence.features[1].parsed_location().unwrap(),
+        Location::Range(Position::Before(1), Position::After(81))
+    );
+}
+
+#[test]
+fn test_extract_feature_sequence() {
+    let (sequence, _) = parse_sequence_record(RECORD_3).unwrap();
+    let cds = sequence.feature_sequence(&sequence.features[2]).unwrap();
+    assert_eq!(cds.len(), 657);
+    assert!(cds.starts_with(b"atgggtggcaagtggtcaaaacg"));
+    assert_eq!(&cds[..], &sequence.sequence[..657]);
+
+    let sequence_bytes = b"aacgtRYKMbdhvnt";
+    assert_eq!(reverse_complement(sequence_bytes), b"anbdhvKMRYacgtt");
+
+    let location: Location = "join(complement(4..6),1..2,gap(3))".parse().unwrap();
+    assert_eq!(location.extract(b"acgtacgt", false).unwrap(), b"gtaacnnn");
+    let location: Location = "join(1..2,gap(18446744073709551615))".parse().unwrap();
+    assert_eq!(
+        location.extract(b"acgtacgt", false).unwrap_err(),
+        ExtractError::Unsupported("a gap longer than the sequence")
+    );
+
+    let location: Location = "complement(join(1..2,7..8))".parse().unwrap();
+    assert_eq!(location.extract(b"acgtacgt", false).unwrap(), b"acgt");
+
+    // wraparound on a circular genome
+    let location: Location = "7..2".parse().unwrap();
+    assert_eq!(location.extract(b"acgtacgt", true).unwrap(), b"gtac");
+    assert_eq!(
+        location.extract(b"acgtacgt", false).unwrap_err(),
+        ExtractError::OutOfBounds {
+            start: 7,
+            end: 2,
+            length: 8
+        }
+    );
+
+    let location: Location = "1..9".parse().unwrap();
+    assert!(location.extract(b"acgtacgt", false).is_err());
+    let location: Location = "join(1..2,J00194.1:1..2)".parse().unwrap();
+    assert_eq!(
+        location.extract(b"acgtacgt", false).unwrap_err(),
+        ExtractError::RemoteLocation(String::from("J00194.1"))
+    );
+}
+
+#[test]
+fn test_translate_features() {
+    for record in [RECORD, RECORD_2, RECORD_3] {
+        let (sequence, proteins) = parse_sequence_record(record).unwrap();
+        let cds = sequence
+            .features
+            .iter()
+            .find(|feature| feature.feature_type == Some(FeatureType::Cds))
+            .unwrap();
+        assert_eq!(
+            sequence.translate_feature(cds).unwrap(),
+            proteins[0].sequence
+        );
+        assert!(!proteins[0].translated);
+    }
+
+    let code = GeneticCode::standard();
+    assert_eq!(code.translate_codon(b"atg"), b'M');
+    assert_eq!(code.translate_codon(b"TAA"), b'*');
+    assert_eq!(code.translate_codon(b"aay"), b'N');
+    assert_eq!(code.translate_codon(b"ray"), b'B');
+    assert_eq!(code.translate_codon(b"ctn"), b'L');
+    assert_eq!(code.translate_codon(b"nnn"), b'X');
+    let mitochondrial = GeneticCode::from_id(2).unwrap();
+    assert_eq!(mitochondrial.translate_codon(b"tga"), b'W');
+    assert_eq!(mitochondrial.translate_codon(b"aga"), b'*');
+    assert!(GeneticCode::from_id(7).is_none());
+    // every table NCBI currently assigns
+    for id in (1..=6).chain(9..=14).chain([16]).chain(21..=33) {
+        assert_eq!(GeneticCode::from_id(id).map(|code| code.id), Some(id));
+    }
+    let balanophoraceae = GeneticCode::from_id(32).unwrap();
+    assert_eq!(balanophoraceae.translate_codon(b"tag"), b'W');
+    assert_eq!(balanophoraceae.translate_codon(b"tga"), b'*');
+
+    // alternative start codon, internal stop and terminal stop
+    let options = TranslationOptions {
+        genetic_code: GeneticCode::from_id(11).unwrap(),
+        ..TranslationOptions::default()
+    };
+    assert_eq!(translate(b"gtgaaataggcctaa", &options), b"MK*A");
+    let options = TranslationOptions {
+        five_prime_partial: true,
+        three_prime_partial: true,
+        codon_start: 2,
+        ..TranslationOptions::default()
+    };
+    assert_eq!(translate(b"agtgaaactg", &options), b"VKL");
+
+    let record = b"LOCUS       AF000008                  30 bp    DNA     linear   VRL 26-JUL-2016
+FEATURES             Location/Qualifiers
+     CDS             join(1..6,11..16)
+                     /pseudo
+                     /transl_table=11
+                     /protein_id=\"AAA00001.1\"
+     CDS             complement(<10..30)
+                     /codon_start=2
+                     /transl_except=(pos:complement(24..26),aa:Sec)
+                     /protein_id=\"AAA00002.1\"
+     CDS             1..9
+                     /transl_table=99
+                     /protein_id=\"AAA00003.1\"
+     CDS             join(1..6,11..16)
+                     /transl_table=11
+                     /protein_id=\"AAA00004.1\"
+     CDS             join(1..6,11..16)
+                     /transl_table=11
+ORIGIN      
+        1 atgaaacccg gctgaataaa tcttcatttc";
+    // the pseudo gene and the CDS without a /protein_id give no protein
+    let (sequence, proteins) = parse_sequence_record(record).unwrap();
+    assert_eq!(proteins.len(), 2);
+    // reverse complement gaaatgaagatttattcagcc, read from the second base up to the partial 3' end
+    assert_eq!(proteins[0].sequence, b"KURFIQP");
+    assert_eq!(proteins[0].protein_id, b"AAA00002.1");
+    assert_eq!(proteins[1].sequence, b"MKAE");
+    assert!(proteins[1].translated);
+    assert!(sequence.translate_feature(&sequence.features[2]).is_err());
+
+    // CDSs across the origin of a circular record, with an exception past it
+    let record = b"LOCUS       AF000023                  30 bp    DNA     circular BCT 26-JUL-2016
+FEATURES             Location/Qualifiers
+     CDS             25..9
+                     /transl_except=(pos:4..6,aa:Sec)
+     CDS             complement(25..9)
+                     /transl_except=(pos:complement(28..30),aa:Sec)
+ORIGIN      
+        1 cccgggtaat tttttttttt ttttatgaaa";
+    let (sequence, _) = parse_sequence_record(record).unwrap();
+    assert_eq!(
+        sequence.translate_feature(&sequence.features[0]).unwrap(),
+        b"MKPU"
+    );
+    // reverse complement ttacccgggtttcat
+    assert_eq!(
+        sequence.translate_feature(&sequence.features[1]).unwrap(),
+        b"LPGUH"
+    );
+    let location: Location = "complement(25..9)".parse().unwrap();
+    assert_eq!(location.offset_of(9, 30), Some(0));
+    assert_eq!(location.offset_of(30, 30), Some(9));
+    assert_eq!(location.offset_of(25, 30), Some(14));
+    assert_eq!(location.offset_of(12, 30), None);
+}
+
+#[test]
+fn test_validate_translations() {
+    for record in [RECORD, RECORD_2, RECORD_3] {
+        let (sequence, _) = parse_sequence_record(record).unwrap();
+        let report = sequence.validate_translations();
+        assert_eq!(report.checked_cds, 1);
+        assert!(report.is_valid());
+    }
+
+    let record = b"LOCUS       AF000009                  30 bp    DNA     linear   VRL 26-JUL-2016
+VERSION     AF000009.1
+FEATURES             Location/Qualifiers
+     CDS             1..15
+                     /protein_id=\"AAA00001.1\"
+                     /translation=\"MKLA\"
+     CDS             1..14
+                     /translation=\"MK\"
+     CDS             <21..30
+                     /translation=\"MKL\"
+     CDS             1..6
+                     /translation=\"MR\"
+     CDS             1..6
+     CDS             1..6
+                     /translation=\"MX\"
+     CDS             1..6
+                     /transl_table=99
+                     /translation=\"MK\"
+ORIGIN      
+        1 atgaaatagg cctgaaaagc gatgaaactg";
+    let (sequence, _) = parse_sequence_record(record).unwrap();
+    let report = sequence.validate_translations();
+    assert_eq!(report.source_id, b"AF000009.1");
+    assert_eq!(report.checked_cds, 6);
+    assert!(!report.is_valid());
+
+    let issues: Vec<(&[u8], &[TranslationIssue])> = report
+        .cds
+        .iter()
+        .map(|cds| (cds.location.as_slice(), cds.issues.as_slice()))
+        .collect();
+    assert_eq!(issues.len(), 5);
+    assert_eq!(report.cds[0].protein_id, b"AAA00001.1");
+    assert_eq!(
+        issues[0],
+        (
+            b"1..15".as_slice(),
+            [TranslationIssue::InternalStops { positions: vec![3] }].as_slice()
+        )
+    );
+    assert_eq!(
+        issues[1].1,
+        [
+            TranslationIssue::Frame {
+                length: 14,
+                codon_start: 1
+            },
+            TranslationIssue::InternalStops { positions: vec![3] },
+            TranslationIssue::LengthMismatch {
+                annotated: 2,
+                translated: 4
+            },
+        ]
+    );
+    assert_eq!(
+        issues[2].1,
+        [
+            TranslationIssue::Frame {
+                length: 10,
+                codon_start: 1
+            },
+            TranslationIssue::CodonStart {
+                annotated: 1,
+                matching: 2
+            },
+        ]
+    );
+    assert_eq!(
+        issues[3].1,
+        [TranslationIssue::Mismatches {
+            count: 1,
+            first: vec![ResidueMismatch {
+                position: 2,
+                annotated: 'R',
+                translated: 'K'
+            }]
+        }]
+    );
+    assert!(matches!(
+        issues[4].1,
+        [TranslationIssue::Untranslatable { .. }]
+    ));
+
+    let json = serde_json::to_string(&report.cds[0]).unwrap();
+    assert_eq!(
+        json,
+        r#"{"protein_id":"AAA00001.1","location":"1..15","issues":[{"issue":"internal_stops","positions":[3]}]}"#
+    );
+}
+
+#[test]
+fn test_validate_record() {
+    for record in [RECORD, RECORD_2, RECORD_3] {
+        let report = validate_record(record).unwrap();
+        assert_eq!(report.issues, []);
+        assert!(report.is_valid());
+    }
+
+    let record = b"LOCUS       AF000010                 130 bp    DNA     linear   VRL 26-JUL-2016
+FEATURES             Location/Qualifiers
+     gene            complement(10..20)
+     gene            100..140
+     gene            1..x
+ORIGIN      
+        1 acgtacgtac gtacgtacgt acgtacgtac gtacgtacgt acgtacgtac gtacgtacgt
+       61 acgtacgtac gtacgtacgt acgta-gtac gtacgtacgt acgtacgtac gtacgtacgt
+      121 acgtacgtac
+//
+";
+    let report = validate_record(record).unwrap();
+    assert!(!report.is_valid());
+    assert_eq!(
+        report.issues,
+        [
+            RecordIssue::OriginNumbering {
+                offset: 382,
+                expected: 120,
+                found: Some(121)
+            },
+            RecordIssue::InvalidCharacters {
+                count: 1,
+                characters: vec!['-'],
+                first_offset: 343
+            },
+            RecordIssue::LengthMismatch {
+                declared: 130,
+                actual: 129
+            },
+            RecordIssue::FeatureOutOfBounds {
+                location: "100..140".to_string(),
+                length: 130
+            },
+            RecordIssue::InvalidLocation {
+                location: "1..x".to_string(),
+                reason: "invalid location at byte 3: expected a position".to_string()
+            },
+        ]
+    );
+    assert_eq!(record[343], b'-');
+    assert!(record[382..].starts_with(b"      121"));
+
+    // a missing line number is reported without shifting the rest
+    let record = b"LOCUS       AF000011                  20 bp    DNA     linear   VRL 26-JUL-2016
+FEATURES             Location/Qualifiers
+ORIGIN      
+          acgtacgtac
+       11 acgtacgtac
+//
+";
+    let report = validate_record(record).unwrap();
+    assert_eq!(
+        report.issues,
+        [RecordIssue::OriginNumbering {
+            offset: 134,
+            expected: 1,
+            found: None
+        }]
+    );
+
+    // a protein's terminal stop counts towards its length
+    let record = b"LOCUS       AAA00005                  12 aa            linear   VRL 26-JUL-2016
+FEATURES             Location/Qualifiers
+ORIGIN      
+        1 mkvlaagiif s*
+//
+";
+    let report = validate_record(record).unwrap();
+    assert!(report.is_valid(), "{:?}", report.issues);
+    let (sequence, _) = parse_sequence_record(record).unwrap();
+    assert_eq!(sequence.sequence, b"mkvlaagiifs*");
+}
+
+/// Yields `data` then fails, like a file whose disk goes away mid-read.
+struct FailingReader<'a> {
+    data: &'a [u8],
+}
+
+impl std::io::Read for FailingReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.data.is_empty() {
+            return Err(std::io::Error::other("disk gone"));
+        }
+        let read = self.data.len().min(buf.len());
+        buf[..read].copy_from_slice(&self.data[..read]);
+        self.data = &self.data[read..];
+        Ok(read)
+    }
+}
+
+/// `records` after `header`, each ended by a `//` line.
+fn flat_file(header: &[u8], records: &[&[u8]]) -> Vec<u8> {
+    let mut file = header.to_vec();
+    for record in records {
+        file.extend_from_slice(record);
+        if !record.ends_with(b"//") {
+            file.extend_from_slice(b"\n//");
+        }
+        file.push(b'\n');
+    }
+    file
+}
+
+#[test]
+fn test_genbank_reader() {
+    let file = flat_file(
+        b"GBVRL1.SEQ          Genetic Sequence Data Bank\n\n",
+        &[RECORD, RECORD_2, RECORD_3],
+    );
+
+    // a tiny buffer makes sure records are assembled across many reads
+    let reader = GenbankReader::new(BufReader::with_capacity(16, file.as_slice()));
+    let records: Vec<_> = reader.map(Result::unwrap).collect();
+    assert_eq!(records.len(), 3);
+    for ((sequence, proteins), record) in records.iter().zip([RECORD, RECORD_2, RECORD_3]) {
+        let (expected, expected_proteins) = parse_sequence_record(record).unwrap();
+        assert_eq!(sequence.accession, expected.accession);
+        assert_eq!(sequence.sequence, expected.sequence);
+        assert_eq!(sequence.features, expected.features);
+        assert_eq!(proteins.len(), expected_proteins.len());
+    }
+
+    let mut reader = GenbankReader::new(file.as_slice());
+    let first = reader.next_record().unwrap().unwrap();
+    assert_eq!(first, RECORD.strip_suffix(b"//").unwrap());
+    assert_eq!(reader.count(), 2);
+
+    assert_eq!(GenbankReader::new(&b"no records here\n"[..]).count(), 0);
+
+    let failing = FailingReader {
+        data: &RECORD[..100],
+    };
+    let mut reader = GenbankReader::new(BufReader::new(failing));
+    let error = reader.next().unwrap().unwrap_err();
+    assert!(matches!(
+        error,
+        ParseError::Io {
+            kind: std::io::ErrorKind::Other,
+            ..
+        }
+    ));
+    assert_eq!(error.accession(), "AB000048");
+    assert_eq!(error.offset(), None);
+    assert!(reader.next().is_none());
+}
+
+#[test]
+fn test_release_header() {
+    let header = b"GBVRL1.SEQ          Genetic Sequence Data Bank
+                          October 15 2023
+
+                NCBI-GenBank Flat File Release 258.0
+
+                           Viral Sequences (Part 1)
+
+       3 loci,        4812 bases, from        3 reported sequences
+
+
+";
+    let file = flat_file(header, &[RECORD, RECORD_2, RECORD_3]);
+
+    let mut reader = GenbankReader::new(file.as_slice());
+    let header = reader.release_header().unwrap().unwrap().clone();
+    assert_eq!(header.file_name, b"GBVRL1.SEQ");
+    assert_eq!(header.release, b"258.0");
+    assert_eq!(header.date, NaiveDate::from_ymd_opt(2023, 10, 15));
+    assert_eq!(header.title, b"Viral Sequences (Part 1)");
+    assert_eq!((header.loci, header.bases, header.sequences), (3, 4812, 3));
+    assert_eq!(reader.map(Result::unwrap).count(), header.loci);
+
+    // files without a header, e.g. from efetch, keep their first record
+    let efetch = flat_file(b"", &[RECORD, RECORD_2]);
+    let mut reader = GenbankReader::new(efetch.as_slice());
+    assert_eq!(reader.release_header().unwrap(), None);
+    let accessions: Vec<Vec<u8>> = reader.map(|record| record.unwrap().0.accession).collect();
+    assert_eq!(accessions, [b"AB000048".to_vec(), b"AF148865".to_vec()]);
+
+    // text before the first LOCUS line that isn't a release header is skipped
+    let mut reader = GenbankReader::new(&b"some notes\n\nLOCUS       X 10 bp\n//\n"[..]);
+    assert_eq!(reader.release_header().unwrap(), None);
+    assert_eq!(
+        reader.next_record().unwrap().unwrap(),
+        b"LOCUS       X 10 bp\n"
+    );
+    assert!(reader.next_record().unwrap().is_none());
+}
+
+#[test]
+fn test_pipeline() {
+    let mut file = b"GBVRL1.SEQ          Genetic Sequence Data Bank\n".to_vec();
+    for i in 0..200 {
+        file.extend_from_slice(format!("LOCUS       R{:05}\n//\n", i).as_bytes());
+    }
+    let expected: Vec<String> = (0..200).map(|i| format!("R{:05}", i)).collect();
+    let name = |record: &[u8]| {
+        // later records finish first within a batch
+        let number: u64 = std::str::from_utf8(&record[13..18])
+            .unwrap()
+            .parse()
+            .unwrap();
+        std::thread::sleep(std::time::Duration::from_micros(200 - number));
+        Ok(String::from_utf8(record[12..18].to_vec()).unwrap())
+    };
+
+    for (batch_size, max_in_flight) in [(1, 1), (7, 3), (64, 16), (1000, 2)] {
+        let pipeline = Pipeline::new()
+            .batch_size(batch_size)
+            .max_in_flight(max_in_flight);
+        let mut names = Vec::new();
+        pipeline
+            .run(GenbankReader::new(file.as_slice()), name, |name| {
+                names.push(name.unwrap());
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(names, expected);
+    }
+
+    // a failing write stops the pipeline instead of hanging it
+    let mut written = 0;
+    let error = Pipeline::new()
+        .batch_size(3)
+        .max_in_flight(2)
+        .run(GenbankReader::new(file.as_slice()), name, |_| {
+            written += 1;
+            if written == 10 {
+                return Err(std::io::Error::other("disk full"));
+            }
+            Ok(())
+        })
+        .unwrap_err();
+    assert_eq!(error.to_string(), "disk full");
+    assert_eq!(written, 10);
+
+    // as does a failing read, after everything read before it has been written
+    let failing = FailingReader { data: &file[..500] };
+    let mut names = Vec::new();
+    let error = Pipeline::new()
+        .batch_size(2)
+        .run(GenbankReader::new(BufReader::new(failing)), name, |name| {
+            names.push(name.unwrap());
+            Ok(())
+        })
+        .unwrap_err();
+    assert_eq!(error.to_string(), "disk gone");
+    assert_eq!(names, expected[..names.len()]);
+    assert!(!names.is_empty());
+
+    // including the records of a batch the read failed part way through
+    let failing = FailingReader { data: &file[..500] };
+    let mut names = Vec::new();
+    let error = Pipeline::new()
+        .batch_size(1000)
+        .run(GenbankReader::new(BufReader::new(failing)), name, |name| {
+            names.push(name.unwrap());
+            Ok(())
+        })
+        .unwrap_err();
+    assert_eq!(error.to_string(), "disk gone");
+    assert_eq!(names, expected[..20]);
+
+    // truncated records are written as errors in their place
+    let truncated = b"LOCUS       R00000\nLOCUS       R00001\n//\nLOCUS       R00002\n";
+    let mut results = Vec::new();
+    Pipeline::new()
+        .batch_size(2)
+        .run(GenbankReader::new(&truncated[..]), name, |result| {
+            results.push(result.map_err(|error| error.to_string()));
+            Ok(())
+        })
+        .unwrap();
+    assert_eq!(
+        results,
+        [
+            Err(String::from("R00000: record ends without a // line")),
+            Ok(String::from("R00001")),
+            Err(String::from("R00002: record ends without a // line")),
+        ]
+    );
+}
+
+#[test]
+fn test_record_splitting() {
+    // CRLF line endings, trailing whitespace after `//`, text between records and a LOCUS that
+    // isn't at the start of a line
+    let file = b"LOCUS       A 10 bp\r\nCOMMENT     see LOCUS B\r\n   LOCUS in text\r\n//  \t\r\n\
+notes between records\n\
+LOCUSX\n\
+LOCUS\tB 10 bp\n//\n\n";
+    let mut reader = GenbankReader::new(&file[..]);
+    assert_eq!(
+        reader.next_record().unwrap().unwrap(),
+        b"LOCUS       A 10 bp\nCOMMENT     see LOCUS B\n   LOCUS in text\n"
+    );
+    assert_eq!(reader.next_record().unwrap().unwrap(), b"LOCUS\tB 10 bp\n");
+    assert_eq!(reader.next_record().unwrap(), None);
+
+    // the real records split the same way with CRLF line endings
+    let crlf: Vec<u8> = flat_file(b"", &[RECORD, RECORD_2, RECORD_3])
+        .split(|&byte| byte == b'\n')
+        .collect::<Vec<_>>()
+        .join(&b"\r\n"[..]);
+    let accessions: Vec<Vec<u8>> = GenbankReader::new(crlf.as_slice())
+        .map(|record| record.unwrap().0.accession)
+        .collect();
+    assert_eq!(
+        accessions,
+        [
+            b"AB000048".to_vec(),
+            b"AF148865".to_vec(),
+            b"AF219750".to_vec()
+        ]
+    );
+
+    // a record cut short is reported instead of parsed, and reading carries on after it
+    let mut file = flat_file(b"", &[RECORD]);
+    file.extend_from_slice(&RECORD_2[..200]);
+    file.push(b'\n');
+    file.extend_from_slice(&flat_file(b"", &[RECORD_3]));
+    file.extend_from_slice(&RECORD_2[..300]);
+    let results: Vec<_> = GenbankReader::new(file.as_slice()).collect();
+    assert_eq!(results.len(), 4);
+    assert!(results[0].is_ok());
+    assert_eq!(
+        results[1].as_ref().unwrap_err(),
+        &ParseError::TruncatedRecord {
+            accession: String::from("AF148865"),
+        }
+    );
+    assert_eq!(results[2].as_ref().unwrap().0.accession, b"AF219750");
+    assert_eq!(
+        results[3].as_ref().unwrap_err().to_string(),
+        "AF148865: record ends without a // line"
+    );
+}
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+#[test]
+fn test_gzip_input() {
+    let file = flat_file(
+        b"GBVRL1.SEQ          Genetic Sequence Data Bank\n\n",
+        &[RECORD],
+    );
+    let second = flat_file(b"", &[RECORD_2]);
+
+    let accessions = |input: Vec<u8>| -> Vec<Vec<u8>> {
+        let reader = decompress(std::io::Cursor::new(input)).unwrap();
+        GenbankReader::new(reader)
+            .map(|record| record.unwrap().0.accession)
+            .collect()
+    };
+    let expected = vec![b"AB000048".to_vec(), b"AF148865".to_vec()];
+
+    let plain = [file.as_slice(), second.as_slice()].concat();
+    assert_eq!(accessions(plain.clone()), expected);
+    assert_eq!(accessions(gzip(&plain)), expected);
+    // concatenated members, the way NCBI and `cat a.gz b.gz` write them
+    let members = [gzip(&file), gzip(&second)].concat();
+    assert_eq!(accessions(members.clone()), expected);
+
+    let path = std::env::temp_dir().join(format!("genbank-parser-{}.seq.gz", std::process::id()));
+    std::fs::write(&path, &members).unwrap();
+    let records: Vec<_> = GenbankReader::open(&path).unwrap().collect();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(records.len(), 2);
+    assert!(records.iter().all(Result::is_ok));
+}
+
+#[test]
+fn test_records_without_features() {
+    let record = b"LOCUS       AB000001                  20 bp    DNA     linear   PAT 26-JUL-2016
+DEFINITION  Sequence 1 from patent.
+ACCESSION   AB000001
+VERSION     AB000001.1
+KEYWORDS    .
+SOURCE      unknown
+  ORGANISM  unknown
+            Unclassified.
+ORIGIN
+        1 acgtacgtac gtacgtacgt
+//";
+    let (sequence, proteins) = parse_sequence_record(record).unwrap();
+    assert_eq!(sequence.version, b"AB000001.1");
+    assert_eq!(sequence.sequence, b"acgtacgtacgtacgtacgt");
+    assert!(sequence.features.is_empty());
+    assert!(proteins.is_empty());
+    assert!(validate_record(record).unwrap().is_valid());
+
+    // sections after the feature table, e.g. CONTIG, end it as well as ORIGIN does
+    let record = b"LOCUS       AB000002                  20 bp    DNA     linear   CON 26-JUL-2016
+VERSION     AB000002.1
+FEATURES             Location/Qualifiers
+     source          1..20
+                     /mol_type=\"genomic DNA\"
+CONTIG      join(AB000001.1:1..20)
+//";
+    let (sequence, _) = parse_sequence_record(record).unwrap();
+    assert!(sequence.sequence.is_empty());
+    assert_eq!(sequence.features.len(), 1);
+    assert_eq!(sequence.features[0].location, b"1..20");
+    assert!(validate_record(record).unwrap().is_valid());
+
+    let record = b"LOCUS       AB000003                  20 bp    DNA     linear   CON 26-JUL-2016
+VERSION     AB000003.1
+FEATURES             Location/Qualifiers
+     source          1..20
+                     /mol_type=\"genomic DNA\"
+BASE COUNT        5 a      5 c      5 g      5 t
+ORIGIN      
+        1 acgtacgtac gtacgtacgt
+//";
+    let (sequence, _) = parse_sequence_record(record).unwrap();
+    assert_eq!(sequence.sequence, b"acgtacgtacgtacgtacgt");
+    assert_eq!(sequence.features.len(), 1);
+}
+
+#[test]
+fn test_write_genbank() {
+    for record in [RECORD, RECORD_2, RECORD_3] {
+        let (sequence, proteins) = parse_sequence_record(record).unwrap();
+        let mut written = Vec::new();
+        write_genbank(&mut written, &sequence).unwrap();
+
+        let (reparsed, reparsed_proteins) = parse_sequence_record(&written).unwrap();
+        assert_eq!(
+            serde_json::to_value(&reparsed).unwrap(),
+            serde_json::to_value(&sequence).unwrap()
+        );
+        assert_eq!(
+            serde_json::to_value(&reparsed_proteins).unwrap(),
+            serde_json::to_value(&proteins).unwrap()
+        );
+    }
+
+    // the layout is NCBI's, so a record whose SOURCE is its organism comes out as it went in
+    for record in [RECORD, RECORD_2] {
+        let (sequence, _) = parse_sequence_record(record).unwrap();
+        let mut written = Vec::new();
+        write_genbank(&mut written, &sequence).unwrap();
+        let mut expected = record.to_vec();
+        if !expected.ends_with(b"//") {
+            expected.extend_from_slice(b"\n//");
+        }
+        expected.push(b'\n');
+        assert_eq!(
+            String::from_utf8(written).unwrap(),
+            String::from_utf8(expected).unwrap()
+        );
+    }
+
+    // long values wrap at 79 columns without a line that reads as a new qualifier
+    let (mut sequence, _) = parse_sequence_record(RECORD_2).unwrap();
+    let note = b"see the \"quoted\" remark about isolate A/B and the /note qualifier \
+        of the reference strain, whose sequence is described in detail elsewhere /other";
+    sequence.features[0]
+        .qualifiers
+        .push((b"note".to_vec(), note.to_vec()));
+    sequence.features[0].location = (1..=40)
+        .map(|i| format!("{}..{}", i * 2, i * 2 + 1))
+        .collect::<Vec<_>>()
+        .join(",")
+        .into_bytes();
+    let mut written = Vec::new();
+    write_genbank(&mut written, &sequence).unwrap();
+    assert!(written.split(|&b| b == b'\n').all(|line| line.len() <= 79));
+    let (reparsed, _) = parse_sequence_record(&written).unwrap();
+    assert_eq!(reparsed.features[0], sequence.features[0]);
+
+    // numeric qualifiers are unquoted, and a word longer than a line is split
+    let url = format!("https://example.org/{}", "a".repeat(80));
+    sequence.features[1]
+        .qualifiers
+        .push((b"transl_table".to_vec(), b"11".to_vec()));
+    sequence.features[1]
+        .qualifiers
+        .push((b"note".to_vec(), url.clone().into_bytes()));
+    let mut written = Vec::new();
+    write_genbank(&mut written, &sequence).unwrap();
+    assert!(written.split(|&b| b == b'\n').all(|line| line.len() <= 79));
+    let written = String::from_utf8(written).unwrap();
+    assert!(written.contains("\n                     /transl_table=11\n"));
+    let (reparsed, _) = parse_sequence_record(written.as_bytes()).unwrap();
+    assert_eq!(
+        reparsed.features[1].qualifier(b"transl_table"),
+        Some(&b"11"[..])
+    );
+    let note = reparsed.features[1].qualifier(b"note").unwrap();
+    assert_eq!(
+        note.iter()
+            .filter(|&&b| b != b' ')
+            .copied()
+            .collect::<Vec<_>>(),
+        url.into_bytes()
+    );
+}
+
+#[test]
+fn test_fasta() {
+    let (sequence, proteins) = parse_sequence_record(RECORD_3).unwrap();
+
+    let mut written = Vec::new();
+    Fasta::new(genbank_parser::DEFAULT_SEQUENCE_DEFLINE)
+        .unwrap()
+        .write_sequence(&mut written, &sequence)
+        .unwrap();
+    let written = String::from_utf8(written).unwrap();
+    let lines: Vec<&str> = written.lines().collect();
+    assert_eq!(
+        lines[0],
+        ">AF219750.1 HIV-1 LTS 38d from Australia nef protein (nef) gene, complete cds."
+    );
+    assert_eq!(lines.len(), 1 + 754usize.div_ceil(70));
+    assert!(lines[1..].iter().all(|line| line.len() <= 70));
+    assert_eq!(lines[1..].concat().as_bytes(), sequence.sequence);
+
+    let mut written = Vec::new();
+    Fasta::new(genbank_parser::DEFAULT_PROTEIN_DEFLINE)
+        .unwrap()
+        .write_protein(&mut written, &proteins[0], &sequence)
+        .unwrap();
+    assert!(written.starts_with(b">AAG44221.1 nef protein [Human immunodeficiency virus 1]\nMGGKW"));
+
+    // unknown values are left empty, and a width of 0 keeps the residues on one line
+    let fasta = Fasta::new("{locus}|{gene}|{product}|{length} {location}")
+        .unwrap()
+        .line_width(0);
+    let mut written = Vec::new();
+    fasta
+        .write_protein(&mut written, &proteins[0], &sequence)
+        .unwrap();
+    fasta.write_sequence(&mut written, &sequence).unwrap();
+    let written = String::from_utf8(written).unwrap();
+    let lines: Vec<&str> = written.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0], ">AF219750|nef|nef protein|218 1..657");
+    assert_eq!(lines[1].as_bytes(), proteins[0].sequence);
+    assert_eq!(lines[2], ">AF219750|||754");
+
+    assert_eq!(
+        Fasta::new("{version} {organsim}").unwrap_err(),
+        DeflineError::UnknownField(String::from("organsim"))
+    );
+    assert_eq!(
+        Fasta::new("{version").unwrap_err(),
+        DeflineError::UnclosedField
+    );
+}
+
+#[test]
+fn test_gff3() {
+    let (sequence, _) = parse_sequence_record(RECORD_3).unwrap();
+    let mut written = GFF3_HEADER.as_bytes().to_vec();
+    write_gff3(&mut written, &sequence).unwrap();
+    written.extend_from_slice(GFF3_FASTA_DIRECTIVE.as_bytes());
+    write_gff3_sequence(&mut written, &sequence).unwrap();
+    let written = String::from_utf8(written).unwrap();
+    let lines: Vec<&str> = written.lines().collect();
+    assert_eq!(lines[0], "##gff-version 3");
+    assert_eq!(lines[1], "##sequence-region AF219750.1 1 754");
+    assert!(lines[2].starts_with(
+        "AF219750.1\tGenBank\tregion\t1\t754\t.\t+\t.\tID=AF219750.1:source-1;gbkey=source;"
+    ));
+    assert!(lines[2].contains(";Dbxref=taxon:11676;"));
+    assert_eq!(
+        lines[3],
+        "AF219750.1\tGenBank\tgene\t1\t657\t.\t+\t.\tID=AF219750.1:gene-nef;Name=nef;gbkey=gene;gene=nef"
+    );
+    assert_eq!(
+        lines[4],
+        "AF219750.1\tGenBank\tCDS\t1\t657\t.\t+\t0\tID=AF219750.1:cds-AAG44221.1;\
+         Parent=AF219750.1:gene-nef;Name=AAG44221.1;gbkey=CDS;gene=nef;codon_start=1;\
+         product=nef protein;protein_id=AAG44221.1"
+    );
+    assert_eq!(lines[5], "##FASTA");
+    assert_eq!(lines[6], ">AF219750.1");
+    assert_eq!(lines.len(), 7 + 754usize.div_ceil(60));
+    assert_eq!(lines[7..].concat().as_bytes(), sequence.sequence);
+
+    let record = b"LOCUS       AF000020                  60 bp    DNA     circular VRL 26-JUL-2016
+VERSION     AF000020.1
+FEATURES             Location/Qualifiers
+     source          1..60
+     gene            complement(1..40)
+                     /locus_tag=\"T1\"
+     mRNA            complement(join(1..10,20..40))
+                     /locus_tag=\"T1\"
+     CDS             complement(join(1..10,20..40))
+                     /locus_tag=\"T1\"
+                     /codon_start=2
+                     /note=\"a; b\"
+                     /pseudo
+     misc_feature    55..5
+ORIGIN      
+        1 acgtacgtac acgtacgtac acgtacgtac acgtacgtac acgtacgtac acgtacgtac
+//";
+    let (sequence, _) = parse_sequence_record(record).unwrap();
+    let mut written = Vec::new();
+    write_gff3(&mut written, &sequence).unwrap();
+    let written = String::from_utf8(written).unwrap();
+    let lines: Vec<&str> = written.lines().collect();
+    assert_eq!(
+        lines[1],
+        "AF000020.1\tGenBank\tregion\t1\t60\t.\t+\t.\tID=AF000020.1:source-1;Is_circular=true;gbkey=source"
+    );
+    assert_eq!(
+        lines[3],
+        "AF000020.1\tGenBank\tmRNA\t20\t40\t.\t-\t.\tID=AF000020.1:rna-T1;\
+         Parent=AF000020.1:gene-T1;gbkey=mRNA;locus_tag=T1"
+    );
+    // the stretches of a joined CDS share its ID, with the phase of each
+    let cds = "ID=AF000020.1:cds-T1;Parent=AF000020.1:rna-T1;gbkey=CDS;locus_tag=T1;\
+               codon_start=2;Note=a%3B b;pseudo=true";
+    assert_eq!(
+        lines[5],
+        format!("AF000020.1\tGenBank\tCDS\t20\t40\t.\t-\t1\t{}", cds)
+    );
+    assert_eq!(
+        lines[6],
+        format!("AF000020.1\tGenBank\tCDS\t1\t10\t.\t-\t1\t{}", cds)
+    );
+    // a feature across the origin ends past the length of the sequence
+    assert_eq!(
+        lines[7],
+        "AF000020.1\tGenBank\tsequence_feature\t55\t65\t.\t+\t.\tID=AF000020.1:misc_feature-1;gbkey=misc_feature"
+    );
+    assert_eq!(lines.len(), 8);
+
+    // features outside the record, or across the origin of a linear one, are left out
+    let (sequence, _) = parse_sequence_record(MALFORMED_LOCATIONS).unwrap();
+    let mut written = Vec::new();
+    write_gff3(&mut written, &sequence).unwrap();
+    let written = String::from_utf8(written).unwrap();
+    let lines: Vec<&str> = written.lines().collect();
+    assert_eq!(
+        lines[1..],
+        ["AF000022.1\tGenBank\tCDS\t1\t30\t.\t+\t0\tID=AF000022.1:cds-1;gbkey=CDS;codon_start=0"]
+    );
+}
+
+/// A linear record with features that don't fit on it, and a `/codon_start` out of range
+const MALFORMED_LOCATIONS: &[u8] =
+    b"LOCUS       AF000022                  50 bp    DNA     linear   VRL 26-JUL-2016
+VERSION     AF000022.1
+FEATURES             Location/Qualifiers
+     gene            40..10
+     CDS             complement(200..100)
+     CDS             1..30
+                     /codon_start=0
+     misc_feature    45..60
+ORIGIN      
+        1 acgtacgtac acgtacgtac acgtacgtac acgtacgtac acgtacgtac
+//";
+
+#[test]
+fn test_gtf_and_bed() {
+    let record = b"LOCUS       AF000021                  60 bp    DNA     linear   VRL 26-JUL-2016
+VERSION     AF000021.1
+FEATURES             Location/Qualifiers
+     source          1..60
+     gene            complement(1..40)
+                     /locus_tag=\"T1\"
+                     /gene=\"abc\"
+     mRNA            complement(join(1..10,20..40))
+                     /locus_tag=\"T1\"
+     CDS             complement(join(1..10,20..40))
+                     /locus_tag=\"T1\"
+                     /codon_start=2
+     CDS             join(45..50,50..57)
+                     /protein_id=\"AAA00001.1\"
+     misc_feature    58..60
+ORIGIN      
+        1 acgtacgtac acgtacgtac acgtacgtac acgtacgtac acgtacgtac acgtacgtac
+//";
+    let (sequence, _) = parse_sequence_record(record).unwrap();
+
+    let mut written = Vec::new();
+    write_gtf(&mut written, &sequence).unwrap();
+    let written = String::from_utf8(written).unwrap();
+    let lines: Vec<&str> = written.lines().collect();
+    let gene = "gene_id \"AF000021.1:gene-T1\"; transcript_id \"";
+    let transcript = "gene_id \"AF000021.1:gene-T1\"; transcript_id \"AF000021.1:rna-T1\"; \
+                      gene_name \"abc\";";
+    assert_eq!(
+        lines[0],
+        format!(
+            "AF000021.1\tGenBank\tgene\t1\t40\t.\t-\t.\t{}\"; gene_name \"abc\";",
+            gene
+        )
+    );
+    assert_eq!(
+        lines[1],
+        format!(
+            "AF000021.1\tGenBank\ttranscript\t1\t40\t.\t-\t.\t{}",
+            transcript
+        )
+    );
+    assert_eq!(
+        lines[2],
+        format!("AF000021.1\tGenBank\texon\t20\t40\t.\t-\t.\t{}", transcript)
+    );
+    assert_eq!(
+        lines[3],
+        format!("AF000021.1\tGenBank\texon\t1\t10\t.\t-\t.\t{}", transcript)
+    );
+    assert_eq!(
+        lines[4],
+        format!("AF000021.1\tGenBank\tCDS\t20\t40\t.\t-\t1\t{}", transcript)
+    );
+    assert_eq!(
+        lines[5],
+        format!("AF000021.1\tGenBank\tCDS\t1\t10\t.\t-\t1\t{}", transcript)
+    );
+    // a CDS without a gene or transcript is both itself
+    let cds =
+        "gene_id \"AF000021.1:cds-AAA00001.1\"; transcript_id \"AF000021.1:cds-AAA00001.1\"; \
+               protein_id \"AAA00001.1\";";
+    assert_eq!(
+        lines[6],
+        format!("AF000021.1\tGenBank\ttranscript\t45\t57\t.\t+\t.\t{}", cds)
+    );
+    assert_eq!(
+        lines[9],
+        format!("AF000021.1\tGenBank\tCDS\t45\t50\t.\t+\t0\t{}", cds)
+    );
+    assert_eq!(
+        lines[10],
+        format!("AF000021.1\tGenBank\tCDS\t50\t57\t.\t+\t0\t{}", cds)
+    );
+    assert_eq!(lines.len(), 11);
+
+    let mut written = Vec::new();
+    write_bed(&mut written, &sequence).unwrap();
+    let written = String::from_utf8(written).unwrap();
+    let lines: Vec<&str> = written.lines().collect();
+    assert_eq!(lines[0], "AF000021.1\t0\t40\tAF000021.1:gene-T1\t0\t-");
+    assert_eq!(lines[1], "AF000021.1\t19\t40\tAF000021.1:rna-T1\t0\t-");
+    assert_eq!(lines[2], "AF000021.1\t0\t10\tAF000021.1:rna-T1\t0\t-");
+    assert_eq!(
+        lines[7],
+        "AF000021.1\t57\t60\tAF000021.1:misc_feature-1\t0\t+"
+    );
+    assert_eq!(lines.len(), 8);
+
+    let mut written = Vec::new();
+    write_bed12(&mut written, &sequence).unwrap();
+    let written = String::from_utf8(written).unwrap();
+    let lines: Vec<&str> = written.lines().collect();
+    assert_eq!(
+        lines[0],
+        "AF000021.1\t0\t40\tAF000021.1:gene-T1\t0\t-\t0\t0\t0\t1\t40,\t0,"
+    );
+    // blocks are in order along the sequence, with the CDS as the thick part of its transcript
+    assert_eq!(
+        lines[1],
+        "AF000021.1\t0\t40\tAF000021.1:rna-T1\t0\t-\t0\t40\t0\t2\t10,21,\t0,19,"
+    );
+    // overlapping stretches are merged
+    assert_eq!(
+        lines[3],
+        "AF000021.1\t44\t57\tAF000021.1:cds-AAA00001.1\t0\t+\t44\t57\t0\t1\t13,\t0,"
+    );
+    assert_eq!(
+        lines[4],
+        "AF000021.1\t57\t60\tAF000021.1:misc_feature-1\t0\t+\t57\t57\t0\t1\t3,\t0,"
+    );
+    assert_eq!(lines.len(), 5);
+
+    // features that don't fit on the record are left out
+    let (sequence, _) = parse_sequence_record(MALFORMED_LOCATIONS).unwrap();
+    let mut written = Vec::new();
+    write_bed(&mut written, &sequence).unwrap();
+    assert_eq!(written, b"AF000022.1\t0\t30\tAF000022.1:cds-1\t0\t+\n");
+    let mut written = Vec::new();
+    write_bed12(&mut written, &sequence).unwrap();
+    assert_eq!(
+        written,
+        b"AF000022.1\t0\t30\tAF000022.1:cds-1\t0\t+\t0\t30\t0\t1\t30,\t0,\n"
+    );
+    let mut written = Vec::new();
+    write_gtf(&mut written, &sequence).unwrap();
+    assert_eq!(String::from_utf8(written).unwrap().lines().count(), 3);
+
+    // a feature across the origin of a circular record is split there
+    let record = b"LOCUS       AF000024                  50 bp    DNA     circular BCT 26-JUL-2016
+VERSION     AF000024.1
+FEATURES             Location/Qualifiers
+     misc_feature    45..5
+     misc_feature    complement(48..2)
+ORIGIN      
+        1 acgtacgtac gtacgtacgt acgtacgtac gtacgtacgt acgtacgtac";
+    let (sequence, _) = parse_sequence_record(record).unwrap();
+    let mut written = Vec::new();
+    write_bed(&mut written, &sequence).unwrap();
+    assert_eq!(
+        String::from_utf8(written).unwrap(),
+        "AF000024.1\t44\t50\tAF000024.1:misc_feature-1\t0\t+\n\
+         AF000024.1\t0\t5\tAF000024.1:misc_feature-1\t0\t+\n\
+         AF000024.1\t0\t2\tAF000024.1:misc_feature-2\t0\t-\n\
+         AF000024.1\t47\t50\tAF000024.1:misc_feature-2\t0\t-\n"
+    );
+    let mut written = Vec::new();
+    write_bed12(&mut written, &sequence).unwrap();
+    assert_eq!(
+        String::from_utf8(written).unwrap(),
+        "AF000024.1\t0\t50\tAF000024.1:misc_feature-1\t0\t+\t0\t0\t0\t2\t5,6,\t0,44,\n\
+         AF000024.1\t0\t50\tAF000024.1:misc_feature-2\t0\t-\t0\t0\t0\t2\t2,3,\t0,47,\n"
+    );
+}
+
+#[test]
+fn test_parse_embl_record() {
+    let (genbank, genbank_proteins) = parse_sequence_record(RECORD_3).unwrap();
+    let (embl, embl_proteins) = parse_embl_record(EMBL_RECORD).unwrap();
+
+    // the feature table and everything taken from it come out the same
+    assert_eq!(
+        serde_json::to_value(&embl.features).unwrap(),
+        serde_json::to_value(&genbank.features).unwrap()
+    );
+    assert_eq!(
+        serde_json::to_value(&embl_proteins).unwrap(),
+        serde_json::to_value(&genbank_proteins).unwrap()
+    );
+    assert_eq!(embl.sequence, genbank.sequence);
+    assert_eq!(embl.mol_type, b"genomic DNA");
+
+    assert_eq!(embl.locus.name, b"AF219750");
+    assert_eq!(embl.locus.length, 754);
+    assert_eq!(embl.locus.unit, LengthUnit::BasePairs);
+    assert_eq!(embl.locus.molecule_type, b"DNA");
+    assert_eq!(embl.locus.topology, Some(Topology::Linear));
+    assert_eq!(embl.locus.division, b"VRL");
+    assert_eq!(embl.locus.date, NaiveDate::from_ymd_opt(2001, 1, 2));
+    assert_eq!(embl.accession, genbank.accession);
+    assert!(embl.secondary_accessions.is_empty());
+    assert_eq!(embl.version, genbank.version);
+    assert_eq!(embl.definition, genbank.definition);
+    assert!(embl.keywords.is_empty());
+    // from the source feature, without the common name OS adds
+    assert_eq!(embl.organism, b"Human immunodeficiency virus 1");
+    assert_eq!(embl.taxonomy, genbank.taxonomy);
+
+    assert_eq!(embl.references.len(), 2);
+    let reference = &embl.references[0];
+    assert_eq!(reference.number, 1);
+    assert_eq!(reference.bases, vec![(1, 754)]);
+    assert_eq!(reference.authors.len(), 10);
+    assert_eq!(reference.authors[0], b"Ashton L.");
+    assert_eq!(reference.authors[9], b"Kaldor J.");
+    assert_eq!(
+        reference.title,
+        b"Viral diversity in the nef/LTR region of the HIV-1 genome: associations with \
+          long-term nonprogression"
+    );
+    assert_eq!(reference.journal, b"Unpublished.");
+    let reference = &embl.references[1];
+    assert_eq!(reference.number, 2);
+    assert!(reference.title.is_empty());
+    assert!(reference
+        .journal
+        .starts_with(b"Submitted (23-DEC-1999) to the INSDC. AIDS Molecular Biology Unit,"));
+
+    // older ID lines, secondary accessions and a comment
+    let record = b"ID   X56734   standard; RNA; PLN; 12 BP.
+AC   X56734; S46826;
+SV   X56734.1
+DT   25-NOV-2005 (Rel. 85, Last updated, Version 11)
+PR   Project:PRJNA12345;
+CC   first line
+CC
+CC   second paragraph
+SQ   Sequence 12 BP; 3 A; 3 C; 3 G; 3 T; 0 other;
+     acgtacgtac gt                                                            12
+//";
+    let (sequence, proteins) = parse_record(record).unwrap();
+    assert!(proteins.is_empty());
+    assert_eq!(sequence.locus.name, b"X56734");
+    assert_eq!(sequence.locus.molecule_type, b"RNA");
+    assert_eq!(sequence.locus.division, b"PLN");
+    assert_eq!(sequence.locus.topology, None);
+    assert_eq!(sequence.accession, b"X56734");
+    assert_eq!(sequence.secondary_accessions, vec![b"S46826".to_vec()]);
+    assert_eq!(sequence.version, b"X56734.1");
+    assert_eq!(sequence.dblinks[0].database, DbLinkDatabase::BioProject);
+    assert_eq!(sequence.dblinks[0].ids, vec![b"PRJNA12345".to_vec()]);
+    assert_eq!(sequence.comment, b"first line\n\nsecond paragraph");
+    assert_eq!(sequence.sequence, b"acgtacgtacgt");
+    assert!(sequence.features.is_empty());
+
+    // an unfamiliar data class and division don't fail the record
+    let record = b"ID   X56735; SV 2; circular; genomic DNA; NEW; XYZ; 12 BP; extra words.
+SQ   Sequence 12 BP; 3 A; 3 C; 3 G; 3 T; 0 other;
+     acgtacgtac gt                                                            12
+//";
+    let locus = parse_record(record).unwrap().0.locus;
+    assert_eq!(locus.name, b"X56735");
+    assert_eq!(locus.topology, Some(Topology::Circular));
+    assert_eq!(locus.molecule_type, b"DNA");
+    assert_eq!(locus.division, b"XYZ");
+    assert_eq!(locus.length, 12);
+    let record = b"ID   X56736; SV 1; linear; mRNA; NEW; PLN; 12 BP.\nXX";
+    assert_eq!(parse_embl_record(record).unwrap().0.locus.division, b"PLN");
+
+    assert_eq!(
+        parse_embl_record(b"ID   X56734; SV 1; linear; mRNA; STD; PLN;\nXX").unwrap_err(),
+        ParseError::InvalidLocus {
+            accession: String::from("X56734"),
+            reason: "ID line without a sequence length",
+        }
+    );
+
+    // EMBL and GenBank records are told apart by their first line, even in the same file
+    let mut file = EMBL_RECORD.to_vec();
+    file.push(b'\n');
+    file.extend_from_slice(RECORD_3);
+    file.extend_from_slice(b"\n//\n");
+    let records: Vec<_> = GenbankReader::new(BufReader::new(&file[..]))
+        .map(|record| record.unwrap().0)
+        .collect();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].locus.molecule_type, b"DNA");
+    assert_eq!(records[0].sequence, records[1].sequence);
+    assert_eq!(records[0].version, records[1].version);
+}
+
+#[test]
+fn test_validate_embl_record() {
+    let report = validate_record(EMBL_RECORD).unwrap();
+    assert_eq!(report.issues, []);
+    assert_eq!(report.checked_cds, 1);
+    assert!(report.is_valid());
+
+    // EMBL sequence lines end with the position of their last residue
+    let record = b"ID   X00001; SV 1; linear; genomic DNA; STD; VRL; 130 BP.
+SQ   Sequence 130 BP;
+     acgtacgtac gtacgtacgt acgtacgtac gtacgtacgt acgtacgtac gtacgtacgt        60
+     acgtacgtac gtacgtacgt acgta-gtac gtacgtacgt acgtacgtac gtacgtacgt       121
+     acgtacgtac                                                              130
+//";
+    let report = validate_record(record).unwrap();
+    let lines: Vec<&[u8]> = record.split(|&b| b == b'\n').collect();
+    let offset = |line: usize| {
+        lines[..line]
+            .iter()
+            .map(|line| line.len() + 1)
+            .sum::<usize>()
+    };
+    assert_eq!(
+        report.issues,
+        [
+            RecordIssue::OriginNumbering {
+                offset: offset(3),
+                expected: 119,
+                found: Some(121)
+            },
+            RecordIssue::OriginNumbering {
+                offset: offset(4),
+                expected: 131,
+                found: Some(130)
+            },
+            RecordIssue::InvalidCharacters {
+                count: 1,
+                characters: vec!['-'],
+                first_offset: offset(3) + 32
+            },
+            RecordIssue::LengthMismatch {
+                declared: 130,
+                actual: 129
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_parse_sequence_record_errors() {
+    // errors name the record by its accession rather than its LOCUS name
+    let record = b"LOCUS       CONTIG_1                  10 bp    DNA     linear   VRL 26-JUL-2016
+ACCESSION   AF000001
+VERSION     AF000001.1
+short
+ORIGIN      
+        1 acgtacgtac";
+    assert_eq!(
+        parse_sequence_record(record).unwrap_err(),
+        ParseError::LineTooShort {
+            accession: String::from("AF000001"),
+            offset: 124,
+        }
+    );
+    assert_eq!(&record[124..129], b"short");
+    let record = b"LOCUS       CONTIG_1                  10 bp    DNA     linear   VRL 26-JUL-2016
+VERSION     AF000001.1
+short";
+    assert_eq!(
+        parse_sequence_record(record).unwrap_err().accession(),
+        "AF000001.1"
+    );
+
+    // blank lines outside COMMENT are skipped
+    let record = b"LOCUS       AF000004                  10 bp    DNA     linear   VRL 26-JUL-2016
+DEFINITION  blank lines around it.
+
+KEYWORDS    .
+   
+FEATURES             Location/Qualifiers
+     source          1..10
+
+ORIGIN      
+        1 acgtacgtac";
+    let (sequence, _) = parse_sequence_record(record).unwrap();
+    assert_eq!(sequence.definition, b"blank lines around it.");
+    assert_eq!(sequence.features.len(), 1);
+    assert_eq!(sequence.sequence, b"acgtacgtac");
+
+    let record = b"LOCUS       AF000002                  10 bp    DNA     linear   VRL 26-JUL-2016
+VERSION     AF000002.1
+FEATURES             Location/Qualifiers
+     source          1..10
+                     /mol_type=\"genomic DNA\"";
+    // records without ORIGIN, e.g. CONTIG records, have no sequence
+    let (sequence, _) = parse_sequence_record(record).unwrap();
+    assert!(sequence.sequence.is_empty());
+    assert_eq!(sequence.features.len(), 1);
+    assert_eq!(sequence.mol_type, b"genomic DNA");
+
+    let record = b"LOCUS       AF000003                  10 bp    DNA     linear   VRL 26-JUL-2016
+DEFINITION  bad \xff byte.
+ORIGIN      
+        1 acgtacgtac";
+    let error = parse_sequence_record(record).unwrap_err();
+    assert_eq!(error.accession(), "AF000003");
+    assert_eq!(error.offset(), Some(96));
+}
+
 #[test]
 fn test_split_on_delimeter_exclude() {
     let feature_line = b"line1                     /line2                     /line3";